@@ -5,22 +5,38 @@ use crate::optimizer::ir;
 use crate::optimizer::ir::inst::Cc;
 use crate::optimizer::ir::inst::InstData;
 use crate::optimizer::ir::inst::InstId;
+use crate::optimizer::ir::module::Module;
 
 use crate::frontend::parser;
 use crate::frontend::parser::Node;
+use crate::frontend::parser::Type;
 
 use std::collections::HashMap;
 
+type GenResult<T> = Result<T, String>;
+
 /// Structure which is used during generating IR.
 /// insts - already generated instructions
 /// vars  - map (variable name from AST -> instruction number in the IR)
 /// breaks - vector of vectors of indexes (in `insts` vector) of Goto (break) instructions.
 /// cur_loop - index of first instruction of the currently handling loop.
+/// labels - labels of the enclosing labeled loops with the index of the loop's vector in `breaks`
+///          and the first instruction of the loop.
+/// exprs - arithmetic instructions and Loads generated in the current statement, an equal
+///         instruction is reused instead of creating a new one.
+/// line - line of the current statement, it is also the line of the generated instructions.
+/// types - types of the parameters and the local variables.
+/// return_type - type of the function result.
 struct InstBuilder {
     func: ir::function::Function,
     vars: HashMap<String, InstId>,
     breaks: Vec<Vec<InstId>>,
     cur_loop: InstId,
+    labels: Vec<(String, usize, InstId)>,
+    exprs: HashMap<InstData, InstId>,
+    line: Option<usize>,
+    types: HashMap<String, Type>,
+    return_type: Option<Type>,
 }
 
 impl InstBuilder {
@@ -32,6 +48,11 @@ impl InstBuilder {
 
             // Invalid value at the begining
             cur_loop: Default::default(),
+            labels: Vec::new(),
+            exprs: HashMap::new(),
+            line: None,
+            types: HashMap::new(),
+            return_type: None,
         }
     }
 
@@ -45,26 +66,97 @@ impl InstBuilder {
 
         inst_num
     }
+
+    /// Like `find_or_create_constant` returns an equal instruction if it was already generated.
+    /// This is only valid inside of one statement: each statement may be a target of a branch
+    /// and may store to the variables, so `exprs` is cleared at its begining and after a Call.
+    fn find_or_create_inst(&mut self, data: InstData) -> InstId {
+        if let Some(inst) = self.exprs.get(&data) {
+            return *inst;
+        }
+
+        let inst = self.func.create_inst(data.clone());
+        self.exprs.insert(data, inst);
+
+        inst
+    }
 }
 
 impl InstBuilder {
-    fn gen_and_check(&mut self, expr: &Node) -> InstId {
-        let source = self.generate(expr);
-        source.expect("Instruction for expression is not defined")
+    fn gen_and_check(&mut self, expr: &Node) -> GenResult<InstId> {
+        let source = self.generate(expr)?;
+        Ok(source.expect("Instruction for expression is not defined"))
     }
 
-    fn gen_value_assign(&mut self, expr: &Node, dest: InstId) {
-        let source = self.gen_and_check(expr);
+    /// Width of an integer expression: I32 if it is computed in 32 bits and I64 otherwise. None
+    /// is for an expression of literals which fit into i32, it takes the width of the other
+    /// operand.
+    fn width_of(&self, node: &Node) -> Option<Type> {
+        match node {
+            Node::Integer(value) if *value as i32 as i64 == *value => None,
+            Node::Id(name) if self.types.get(name) == Some(&Type::I32) => Some(Type::I32),
+            Node::Cast(_, Type::I32) => Some(Type::I32),
+            Node::Call(name, args, _) if name == "print" => self.width_of(args.last().unwrap()),
+            Node::Neg(child) | Node::Shl(child, _) | Node::Shr(child, _) => self.width_of(child),
+            Node::Add(left, right)
+            | Node::Sub(left, right)
+            | Node::Mul(left, right)
+            | Node::Div(left, right)
+            | Node::Mod(left, right) => self.common_width(left, right),
+            _ => Some(Type::I64),
+        }
+    }
+
+    /// Width at which an operation on two values is performed. If one of them is i64 the other
+    /// one is sign extended.
+    fn common_width(&self, left: &Node, right: &Node) -> Option<Type> {
+        match (self.width_of(left), self.width_of(right)) {
+            (None, None) => None,
+            (Some(Type::I32), Some(Type::I32) | None) | (None, Some(Type::I32)) => Some(Type::I32),
+            _ => Some(Type::I64),
+        }
+    }
+
+    /// Wraps a value to the range of i32.
+    fn truncate(&mut self, value: InstId) -> InstId {
+        self.find_or_create_inst(InstData::Trunc(value))
+    }
+
+    /// Generates an expression and converts its value to the integer type `to`. The value is kept
+    /// as is if `to` is None or bool. A literal is wrapped at compile time before it is emitted.
+    fn gen_converted(&mut self, expr: &Node, to: Option<Type>) -> GenResult<InstId> {
+        if let (Node::Integer(value), Some(Type::I32)) = (expr, to) {
+            return Ok(self.find_or_create_constant(*value as i32 as i64));
+        }
+
+        let value = self.gen_and_check(expr)?;
+        match (self.width_of(expr), to) {
+            (Some(Type::I32), Some(Type::I64)) => {
+                Ok(self.find_or_create_inst(InstData::SignExtend(value)))
+            }
+            (None | Some(Type::I64), Some(Type::I32)) => Ok(self.truncate(value)),
+            _ => Ok(value),
+        }
+    }
+
+    fn gen_value_assign(&mut self, expr: &Node, dest: InstId, ty: Option<Type>) -> GenResult<()> {
+        let source = self.gen_converted(expr, ty)?;
         self.func.create_inst(InstData::Store(source, dest));
+        Ok(())
     }
 
-    fn generate_let(&mut self, name: &String, expr: &Node) {
+    /// A declaration without an initializer is only an Alloc, the variable is stored later.
+    fn generate_let(&mut self, name: &str, init: Option<&Node>) -> GenResult<()> {
         assert_eq!(self.vars.get(name), None);
 
         let id = self.func.create_inst(InstData::Alloc);
-        self.vars.insert((*name).clone(), id);
+        self.vars.insert(name.to_string(), id);
+        self.func.var_names_mut().insert(id, name.to_string());
 
-        self.gen_value_assign(expr, id);
+        match init {
+            Some(expr) => self.gen_value_assign(expr, id, self.types.get(name).copied()),
+            None => Ok(()),
+        }
     }
 }
 
@@ -79,9 +171,44 @@ enum OpType {
 }
 
 impl InstBuilder {
-    fn gen_arith_or_shift(&mut self, left: &Node, right: &Node, op: OpType) -> InstId {
-        let op1 = self.gen_and_check(left);
-        let op2 = self.gen_and_check(right);
+    fn gen_arith_or_shift(&mut self, left: &Node, right: &Node, op: OpType) -> GenResult<InstId> {
+        // Shift amount is not converted, the shifted value has the width of the left operand
+        let (op1, op2, width) = match op {
+            OpType::Shl | OpType::Shr => {
+                let width = self.width_of(left);
+                (self.gen_and_check(left)?, self.gen_and_check(right)?, width)
+            }
+            _ => {
+                let (op1, op2) = self.gen_operands(left, right)?;
+                (op1, op2, self.common_width(left, right))
+            }
+        };
+        let bits = match width {
+            Some(Type::I32) => 32,
+            _ => 64,
+        };
+
+        // Some operations with a constant right operand are known to be invalid at compile time
+        if let InstData::Constant(value) = self.func[op2] {
+            match op {
+                OpType::Div if value == 0 => {
+                    return Err(format!("Division by zero {}", self.location()));
+                }
+                OpType::Mod if value == 0 => {
+                    return Err(format!("Modulo by zero {}", self.location()));
+                }
+                OpType::Shl | OpType::Shr if !(0..bits).contains(&value) => {
+                    return Err(format!(
+                        "Shift amount {} is out of range 0..{} {}",
+                        value,
+                        bits,
+                        self.location()
+                    ));
+                }
+                _ => (),
+            }
+        }
+
         let arith = match op {
             OpType::Add => InstData::Add(op1, op2),
             OpType::Sub => InstData::Sub(op1, op2),
@@ -92,67 +219,156 @@ impl InstBuilder {
             OpType::Shr => InstData::Shr(op1, op2),
         };
 
-        self.func.create_inst(arith)
-    }
-
-    fn gen_operand(&mut self, op: &Node) -> InstId {
-        if let Node::Id(_name) = op {
-            let option = self.generate(op);
-            return option.unwrap();
-        }
-        if let Node::Integer(_val) = op {
-            let option = self.generate(op);
-            return option.unwrap();
+        // Arithmetic of i32 wraps in 32 bits
+        let result = self.find_or_create_inst(arith);
+        if width == Some(Type::I32) {
+            return Ok(self.truncate(result));
         }
+        Ok(result)
+    }
 
-        std::unreachable!("Comparison operand can only be identifier or integer literal");
+    /// Generates two operands of an operation converted to their common width.
+    fn gen_operands(&mut self, left: &Node, right: &Node) -> GenResult<(InstId, InstId)> {
+        let width = self.common_width(left, right);
+        Ok((
+            self.gen_converted(left, width)?,
+            self.gen_converted(right, width)?,
+        ))
     }
 
-    fn gen_operands_cc(&mut self, cond: &Node) -> (InstId, InstId, Cc) {
+    fn gen_operands_cc(&mut self, cond: &Node) -> GenResult<(InstId, InstId, Cc)> {
         let op1;
         let op2;
 
         match cond {
             Node::Eq(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Eq)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Eq))
             }
 
             Node::Ne(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Ne)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Ne))
             }
 
             Node::Le(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Le)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Le))
             }
 
             Node::Ge(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Ge)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Ge))
             }
 
             Node::Lt(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Lt)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Lt))
             }
 
             Node::Gt(child1, child2) => {
-                op1 = self.gen_operand(child1);
-                op2 = self.gen_operand(child2);
-                (op1, op2, Cc::Gt)
+                (op1, op2) = self.gen_operands(child1, child2)?;
+                Ok((op1, op2, Cc::Gt))
+            }
+            // Any other value is a condition which is true when the value is not equal to zero
+            _ => {
+                op1 = self.gen_and_check(cond)?;
+                op2 = self.find_or_create_constant(0);
+                Ok((op1, op2, Cc::Ne))
             }
-            _ => panic!("Expected eq, ne, le, ge, got {}", (*cond)),
         }
     }
 }
 
+/// Returns the value of a condition if it is known at compile time: true, false or a comparison
+/// of two integer literals.
+fn fold_condition(cond: &Node) -> Option<bool> {
+    let (left, right) = match cond {
+        Node::True => return Some(true),
+        Node::False => return Some(false),
+
+        Node::Eq(left, right)
+        | Node::Ne(left, right)
+        | Node::Lt(left, right)
+        | Node::Gt(left, right)
+        | Node::Le(left, right)
+        | Node::Ge(left, right) => (left, right),
+
+        _ => return None,
+    };
+
+    let (l, r) = match (&**left, &**right) {
+        (Node::Integer(l), Node::Integer(r)) => (l, r),
+        _ => return None,
+    };
+
+    match cond {
+        Node::Eq(_, _) => Some(l == r),
+        Node::Ne(_, _) => Some(l != r),
+        Node::Lt(_, _) => Some(l < r),
+        Node::Gt(_, _) => Some(l > r),
+        Node::Le(_, _) => Some(l <= r),
+        Node::Ge(_, _) => Some(l >= r),
+        _ => std::unreachable!(),
+    }
+}
+
+/// Returns true if control never reaches the statement after this one: it returns from the
+/// function on every path or loops forever. A block never falls through if any of its statements
+/// doesn't, the statements after that one are dead code.
+fn never_falls_through(node: &Node) -> bool {
+    match node {
+        Node::Return(..) | Node::ReturnVoid(_) => true,
+        Node::Block(stmts, _) => stmts.iter().any(never_falls_through),
+        Node::If(cond, block, alter, _) => match fold_condition(cond) {
+            Some(true) => never_falls_through(block),
+            Some(false) => alter.as_deref().is_some_and(never_falls_through),
+            None => never_falls_through(block) && alter.as_deref().is_some_and(never_falls_through),
+        },
+        Node::While(cond, block, label, _) => {
+            fold_condition(cond) == Some(true) && !breaks_loop(block, label, false)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if there is a break of the loop with the label in its body. `nested` is set
+/// inside of the inner loops where a break without a label exits the inner loop.
+fn breaks_loop(node: &Node, label: &Option<String>, nested: bool) -> bool {
+    match node {
+        Node::Break(None, _) => !nested,
+        Node::Break(Some(name), _) => label.as_ref() == Some(name),
+        Node::Block(stmts, _) => stmts.iter().any(|stmt| breaks_loop(stmt, label, nested)),
+        Node::If(_, block, alter, _) => {
+            breaks_loop(block, label, nested)
+                || alter
+                    .as_deref()
+                    .is_some_and(|alter| breaks_loop(alter, label, nested))
+        }
+        // The inner loop with the same label hides the outer one
+        Node::While(_, block, inner, _) => {
+            (label.is_none() || inner != label) && breaks_loop(block, label, true)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the statement assigns to the variable.
+fn assigns_to(node: &Node, name: &str) -> bool {
+    match node {
+        Node::Assign(dest, _, _) => dest == name,
+        Node::Block(stmts, _) => stmts.iter().any(|stmt| assigns_to(stmt, name)),
+        Node::If(_, block, alter, _) => {
+            assigns_to(block, name)
+                || alter
+                    .as_deref()
+                    .is_some_and(|alter| assigns_to(alter, name))
+        }
+        Node::While(_, block, _, _) => assigns_to(block, name),
+        _ => false,
+    }
+}
+
 /// Generating IR for the control flow AST nodes
 impl InstBuilder {
     /// Target instruction of the branch is the instruction after the last instruction of the true
@@ -171,33 +387,70 @@ impl InstBuilder {
     /// 2 Goto 4
     /// 3 alter block
     /// 4 Instruction after the branching
-    fn generate_if(&mut self, cond: &Node, block: &Node, alter: &Option<Box<Node>>) {
+    fn generate_if(
+        &mut self,
+        cond: &Node,
+        block: &Node,
+        alter: &Option<Box<Node>>,
+    ) -> GenResult<()> {
+        // (0) If the condition is known at compile time then only the taken block is generated
+        match fold_condition(cond) {
+            Some(true) => return self.generate(block).map(|_| ()),
+            Some(false) => {
+                if let Some(block_ptr) = alter {
+                    self.generate(block_ptr)?;
+                }
+                return Ok(());
+            }
+            None => (),
+        }
+
         // (1) Generate operands of the comparison, compute the condition code
-        let (op1, op2, cc) = self.gen_operands_cc(cond);
+        let (op1, op2, cc) = self.gen_operands_cc(cond)?;
 
         // (2) Create empty IfFalse instruction, add it to the vector and remember its position
         //     in order to write the target instruction later after generating instructions for the
         //     true successor block.
-        let data = InstData::IfFalse(op1, op2, cc, Default::default());
+        let data = InstData::IfFalse(op1, op2, cc, InstId::UNRESOLVED);
         let if_index = self.func.create_inst(data);
 
         // (3) Generate IR instructions for the true successor block.
-        self.generate(block);
+        self.generate(block)?;
 
         // (4) Compute target IR instruction of this If Node. If there is a false successor then
-        //     create a Goto and generate instructions for false successor.
+        //     create a Goto and generate instructions for false successor. The Goto is not needed
+        //     if control never falls through the true successor block.
         let mut if_target = InstId(self.func.len());
         if let Some(block_ptr) = alter {
-            let goto_id = self.func.create_inst(InstData::Goto(Default::default()));
-            if_target.0 += 1;
+            let mut goto_id = None;
+            if !never_falls_through(block) {
+                goto_id = Some(self.func.create_inst(InstData::Goto(InstId::UNRESOLVED)));
+                if_target.0 += 1;
+            }
 
-            self.generate(&**block_ptr);
-            let after_alter = InstId(self.func.len());
-            self.func[goto_id].set_target(after_alter);
+            self.generate(block_ptr)?;
+            if let Some(goto_id) = goto_id {
+                let after_alter = InstId(self.func.len());
+                self.func[goto_id].set_target(after_alter);
+            }
         }
 
         // (5) Complete IfFalse instruction and write its target to remembered position in the vector.
         self.func[if_index].set_target(if_target);
+
+        Ok(())
+    }
+
+    /// Finds the labeled loop, returns the index of its vector of breaks and its first instruction.
+    fn find_label(&self, label: &str) -> (usize, InstId) {
+        let (_, breaks, begin) = self
+            .labels
+            .iter()
+            .rev()
+            .find(|(name, _, _)| name == label)
+            .unwrap_or_else(|| panic!("Label '{} is not declared", label));
+
+        (*breaks, *begin)
     }
 
     fn set_breaks(&mut self) {
@@ -231,35 +484,42 @@ impl InstBuilder {
     /// the last vector and write target instructions (which is instruction after the last instruction)
     /// to these Goto's. At the begining of the generating while we push a new vector there and
     /// pop at the end.
-    fn generate_while(&mut self, cond: &Node, block: &Node) {
+    fn generate_while(
+        &mut self,
+        cond: &Node,
+        block: &Node,
+        label: &Option<String>,
+    ) -> GenResult<()> {
         // (1) Push vector of breaks for this cycle
         self.breaks.push(Vec::new());
 
         // (2) Generate operands of the comparison, compute the condition code
-        let (op1, op2, cc) = self.gen_operands_cc(cond);
+        let cond_begin = self.func.len();
+        let (op1, op2, cc) = self.gen_operands_cc(cond)?;
 
         // (3) Create IfFalse instruction with no target, add it to the vector and remember its position
         //     in order to write the target instruction later after generating instructions for the
         //     block. Remember previous loop position in `old_loop`. Set current loop position.
-        let data = InstData::IfFalse(op1, op2, cc, Default::default());
+        let data = InstData::IfFalse(op1, op2, cc, InstId::UNRESOLVED);
         let if_index = self.func.create_inst(data);
 
         let old_loop = self.cur_loop;
 
-        // (4) Determine the begining of the loop
-        let begin: InstId;
-        if let InstData::Load(_) = self.func[op1] {
-            begin = op1;
-        } else if let InstData::Load(_) = self.func[op2] {
-            begin = op2;
-        } else {
-            begin = if_index;
-        }
+        // (4) Determine the begining of the loop. It is the first instruction computing the
+        //     condition, constants are skipped as they don't need to be recomputed.
+        let begin = (cond_begin..if_index.0)
+            .map(InstId)
+            .find(|id| !matches!(self.func[*id], InstData::Constant(_)))
+            .unwrap_or(if_index);
 
         self.cur_loop = begin;
+        if let Some(label) = label {
+            self.labels
+                .push((label.clone(), self.breaks.len() - 1, begin));
+        }
 
         // (5) Generate IR instructions for the block.
-        self.generate(block);
+        self.generate(block)?;
 
         // (6) Insert at the bottom of the loop's body Goto instruction targeting
         // the begining of the block.
@@ -278,18 +538,27 @@ impl InstBuilder {
 
         // (9) Save remembered previous loop
         self.cur_loop = old_loop;
+        if label.is_some() {
+            self.labels.pop();
+        }
+
+        Ok(())
     }
 
     /// Steps made in this function (except determining begining of the loop) are described in function
     /// `generate_while` therefore these are not given here.
-    fn generate_infinite_loop(&mut self, block: &Node) {
+    fn generate_infinite_loop(&mut self, block: &Node, label: &Option<String>) -> GenResult<()> {
         self.breaks.push(Vec::new());
 
         let loop_begin = InstId(self.func.len());
         let old_loop = self.cur_loop;
         self.cur_loop = loop_begin;
+        if let Some(label) = label {
+            self.labels
+                .push((label.clone(), self.breaks.len() - 1, loop_begin));
+        }
 
-        self.generate(block);
+        self.generate(block)?;
 
         self.func.create_inst(InstData::Goto(loop_begin));
 
@@ -299,158 +568,284 @@ impl InstBuilder {
 
         // Save remembered previous loop
         self.cur_loop = old_loop;
+        if label.is_some() {
+            self.labels.pop();
+        }
+
+        Ok(())
     }
 }
 
 impl InstBuilder {
-    fn generate_call(&mut self, name: &String, arg_nodes: &[Node]) -> Option<InstId> {
+    /// The parser rejects functions named as a built-in one, so print and assert are always the
+    /// built-ins here.
+    fn generate_call(&mut self, name: &str, arg_nodes: &[Node]) -> GenResult<Option<InstId>> {
+        // Built-in print with several arguments is lowered to one call per argument, the
+        // arguments are evaluated and printed from left to right.
+        if name == "print" && arg_nodes.len() > 1 {
+            let mut last = None;
+            for node in arg_nodes {
+                last = self.generate_call(name, std::slice::from_ref(node))?;
+            }
+            return Ok(last);
+        }
+
+        if name == "assert" {
+            self.generate_assert(&arg_nodes[0])?;
+            return Ok(None);
+        }
+
         // Determine or create variables for the arguments
         let mut args = Vec::new();
-        for node in (*arg_nodes).iter() {
-            let ptr = Box::new((*node).clone());
-            let arg = self.gen_and_check(&ptr);
-            args.push(arg);
+        for node in arg_nodes {
+            args.push(self.gen_and_check(node)?);
         }
 
-        let call = InstData::Call((*name).clone(), args);
-        Some(self.func.create_inst(call))
+        // Value of the built-in print is its argument, so the call is lowered to PrintInt
+        // without a value. In a statement the value is just not used.
+        let printed = match name {
+            "print" => Some(args[0]),
+            _ => None,
+        };
+
+        let call = InstData::Call(name.to_string(), None, args);
+        let inst = self.func.create_inst(call);
+        self.exprs.clear();
+
+        Ok(Some(printed.unwrap_or(inst)))
     }
-}
 
-impl InstBuilder {
-    /// Takes an AST node, checks its type and generates the IR
-    fn generate(&mut self, node: &Node) -> Option<InstId> {
-        // When we meet identifier we try to find it in the HashMap and extract from it the number
-        // of the IR variable.
-        if let Node::Id(name) = node {
-            let var_num = *self.vars.get(name).unwrap();
-            if let InstData::Parameter = self.func[var_num] {
-                return Some(var_num);
+    /// Built-in assert traps if its condition doesn't hold, the condition is negated so the Trap
+    /// is on the fall through:
+    ///
+    /// 0 IfFalse negated condition Goto 2
+    /// 1 Trap
+    /// 2 Instruction after the assert
+    fn generate_assert(&mut self, cond: &Node) -> GenResult<()> {
+        match fold_condition(cond) {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                self.func.create_inst(InstData::Trap);
+                return Ok(());
             }
-
-            return Some(self.func.create_inst(InstData::Load(var_num)));
+            None => (),
         }
 
-        // Creates new variable, instruction MoveImm which writes num to this variable and returns
-        // the variable number.
-        if let Node::Integer(num) = node {
-            return Some(self.find_or_create_constant(*num));
-        }
+        let (op1, op2, cc) = self.gen_operands_cc(cond)?;
+        let data = InstData::IfFalse(op1, op2, cc.negate(), InstId::UNRESOLVED);
+        let if_index = self.func.create_inst(data);
+        self.func.create_inst(InstData::Trap);
 
-        if let Node::Let(name, expr) = node {
-            self.generate_let(name, expr);
-            return None;
-        }
+        let after_trap = InstId(self.func.len());
+        self.func[if_index].set_target(after_trap);
 
-        if let Node::Assign(name, expr) = node {
-            let dest = *self.vars.get(name).unwrap();
-            self.gen_value_assign(expr, dest);
-            return None;
-        }
+        Ok(())
+    }
+}
 
-        if let Node::Add(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Add);
-            return Some(dest);
+impl InstBuilder {
+    /// Line and function for a message
+    fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("at line {} in function {}", line, self.func.name()),
+            None => format!("in function {}", self.func.name()),
         }
+    }
 
-        if let Node::Sub(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Sub);
-            return Some(dest);
-        }
+    /// Takes an AST node, checks its type and generates the IR. Instructions of a statement get
+    /// its line unless a nested statement has already set theirs.
+    fn generate(&mut self, node: &Node) -> GenResult<Option<InstId>> {
+        let line = match node.line() {
+            Some(line) => line,
+            None => return self.generate_node(node),
+        };
 
-        if let Node::Mul(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Mul);
-            return Some(dest);
+        let (outer, first) = (self.line, self.func.len());
+        self.line = Some(line);
+        let result = self.generate_node(node);
+        for inst_line in self.func.source_lines_mut()[first..].iter_mut() {
+            if inst_line.is_none() {
+                *inst_line = Some(line);
+            }
         }
+        self.line = outer;
 
-        if let Node::Div(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Div);
-            return Some(dest);
-        }
+        result
+    }
 
-        if let Node::Mod(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Mod);
-            return Some(dest);
+    fn generate_node(&mut self, node: &Node) -> GenResult<Option<InstId>> {
+        // Each statement starts with no reusable expressions
+        if node.line().is_some() {
+            self.exprs.clear();
         }
 
-        if let Node::If(cond, block, alter) = node {
-            self.generate_if(cond, block, alter);
-            return None;
-        }
+        match node {
+            // When we meet identifier we try to find it in the HashMap and extract from it the
+            // number of the IR variable.
+            Node::Id(name) => {
+                let var_num = *self.vars.get(name).unwrap();
+                // A parameter or its value converted at the entry is used as is
+                if !matches!(self.func[var_num], InstData::Alloc) {
+                    return Ok(Some(var_num));
+                }
+
+                Ok(Some(self.find_or_create_inst(InstData::Load(var_num))))
+            }
 
-        if let Node::While(cond, block) = node {
-            if let Node::True = **cond {
-                self.generate_infinite_loop(block);
-            } else if let Node::False = **cond {
-                self.generate(block);
-            } else {
-                self.generate_while(cond, block);
+            Node::Integer(num) => Ok(Some(self.find_or_create_constant(*num))),
+
+            // Boolean values are 1 and 0
+            Node::True => Ok(Some(self.find_or_create_constant(1))),
+            Node::False => Ok(Some(self.find_or_create_constant(0))),
+
+            // Comparison used as a value rather than as a condition of a branch
+            Node::Eq(_, _)
+            | Node::Ne(_, _)
+            | Node::Lt(_, _)
+            | Node::Gt(_, _)
+            | Node::Le(_, _)
+            | Node::Ge(_, _) => {
+                let (op1, op2, cc) = self.gen_operands_cc(node)?;
+                Ok(Some(self.find_or_create_inst(InstData::Cmp(op1, op2, cc))))
             }
 
-            return None;
-        }
+            Node::Let(name, init, _) => {
+                self.generate_let(name, init.as_deref())?;
+                Ok(None)
+            }
 
-        if let Node::Break = node {
-            debug_assert!(!self.breaks.is_empty());
-            let goto_id = self.func.create_inst(InstData::Goto(Default::default()));
-            self.breaks.last_mut().unwrap().push(goto_id);
+            Node::Assign(name, expr, _) => {
+                let dest = *self.vars.get(name).unwrap();
+                self.gen_value_assign(expr, dest, self.types.get(name).copied())?;
+                Ok(None)
+            }
 
-            return None;
-        }
+            Node::Add(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Add)?))
+            }
+            Node::Sub(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Sub)?))
+            }
+            Node::Mul(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Mul)?))
+            }
+            Node::Div(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Div)?))
+            }
+            Node::Mod(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Mod)?))
+            }
+            Node::Shl(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Shl)?))
+            }
+            Node::Shr(left, right) => {
+                Ok(Some(self.gen_arith_or_shift(left, right, OpType::Shr)?))
+            }
 
-        if let Node::Continue = node {
-            let goto = InstData::Goto(self.cur_loop);
-            self.func.create_inst(goto);
+            // Conversion is a no-op when the widths match
+            Node::Cast(val, ty) => Ok(Some(self.gen_converted(val, Some(*ty))?)),
 
-            return None;
-        }
+            Node::Neg(val) => {
+                let var = self.gen_and_check(val)?;
+                let neg = self.find_or_create_inst(InstData::Neg(var));
+                if self.width_of(val) == Some(Type::I32) {
+                    return Ok(Some(self.truncate(neg)));
+                }
+                Ok(Some(neg))
+            }
 
-        if let Node::Block(nodes) = node {
-            for n in &**nodes {
-                self.generate(n);
+            Node::If(cond, block, alter, _) => {
+                self.generate_if(cond, block, alter)?;
+                Ok(None)
             }
 
-            return None;
-        }
+            Node::While(cond, block, label, _) => {
+                // A loop with a condition which is false at compile time is never entered
+                match fold_condition(cond) {
+                    Some(true) => self.generate_infinite_loop(block, label)?,
+                    Some(false) => (),
+                    None => self.generate_while(cond, block, label)?,
+                }
 
-        if let Node::Call(name, arg_nodes, _) = node {
-            let ret_var = self.generate_call(name, arg_nodes);
-            return ret_var;
-        }
+                Ok(None)
+            }
 
-        if let Node::Return(val) = node {
-            let var = self.gen_and_check(val);
-            self.func.create_inst(InstData::Return(var));
-            return None;
-        }
+            Node::Break(label, _) => {
+                debug_assert!(!self.breaks.is_empty());
+                let breaks = match label {
+                    Some(label) => self.find_label(label).0,
+                    None => self.breaks.len() - 1,
+                };
+                let goto_id = self.func.create_inst(InstData::Goto(InstId::UNRESOLVED));
+                self.breaks[breaks].push(goto_id);
 
-        if let Node::Neg(val) = node {
-            let var = self.gen_and_check(val);
-            return Some(self.func.create_inst(InstData::Neg(var)));
-        }
+                Ok(None)
+            }
 
-        if let Node::Shl(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Shl);
-            return Some(dest);
-        }
+            Node::Continue(label, _) => {
+                let target = match label {
+                    Some(label) => self.find_label(label).1,
+                    None => self.cur_loop,
+                };
+                self.func.create_inst(InstData::Goto(target));
 
-        if let Node::Shr(left, right) = node {
-            let dest = self.gen_arith_or_shift(left, right, OpType::Shr);
-            return Some(dest);
-        }
+                Ok(None)
+            }
+
+            Node::Block(nodes, _) => {
+                for n in &**nodes {
+                    self.generate(n)?;
+                }
+
+                Ok(None)
+            }
+
+            Node::Call(name, arg_nodes, _) => self.generate_call(name, arg_nodes),
+
+            Node::FuncRef(name) => Ok(Some(
+                self.find_or_create_inst(InstData::FuncRef(name.clone(), None)),
+            )),
+            Node::CallIndirect(callee, arg_nodes, _) => {
+                let callee = self.gen_and_check(callee)?;
+                let mut args = Vec::new();
+                for node in &**arg_nodes {
+                    args.push(self.gen_and_check(node)?);
+                }
+
+                let inst = self.func.create_inst(InstData::CallIndirect(callee, args));
+                self.exprs.clear();
+                Ok(Some(inst))
+            }
+
+            Node::Return(val, _) => {
+                let var = self.gen_converted(val, self.return_type)?;
+                self.func.create_inst(InstData::Return(var));
+                Ok(None)
+            }
 
-        if let Node::ReturnVoid = node {
-            self.func.create_inst(InstData::ReturnVoid);
-            return None;
+            Node::ReturnVoid(_) => {
+                self.func.create_inst(InstData::ReturnVoid);
+                Ok(None)
+            }
         }
+    }
+}
 
-        std::unreachable!();
+/// Generates IR for every function of the program and resolves the calls between them
+pub fn generate_module(funcs: &[parser::Func]) -> Result<Module, String> {
+    let mut module = Module::new();
+    for func in funcs {
+        module.add_function(generate_instructions(func)?);
     }
+    module.resolve_calls();
+
+    Ok(module)
 }
 
-/// Main function. Generates sequence of IR instructions from AST
-pub fn generate_instructions(func: &parser::Func) -> ir::function::Function {
-    let mut builder = InstBuilder::new(func.name().clone());
+/// Main function. Generates sequence of IR instructions from AST. Fails on the errors which are
+/// found at compile time, e.g. division by a literal zero.
+pub fn generate_instructions(func: &parser::Func) -> Result<ir::function::Function, String> {
+    let mut builder = InstBuilder::new(func.name().to_string());
 
     // First instructions are the parameters of the function. Each parameter corresponds to an IR
     // variable.
@@ -458,9 +853,29 @@ pub fn generate_instructions(func: &parser::Func) -> ir::function::Function {
         let p_id = builder.func.create_inst(InstData::Parameter);
         builder.vars.insert(param.clone(), p_id);
     }
+    *builder.func.params_mut() = func.params().len();
+    *builder.func.return_type_mut() = func.has_return_type();
+    builder.types = func.var_types().clone();
+    builder.return_type = func.return_type();
+
+    // A caller passes a value of any width, a parameter of i32 is truncated at the entry. A
+    // parameter which is assigned in the body is stored to a local variable.
+    for (param, ty) in func.params().iter().zip(func.param_types()) {
+        let mut value = builder.vars[param];
+        if *ty == Type::I32 {
+            value = builder.func.create_inst(InstData::Trunc(value));
+            builder.vars.insert(param.clone(), value);
+        }
+        if func.stmts().iter().any(|stmt| assigns_to(stmt, param)) {
+            let alloc = builder.func.create_inst(InstData::Alloc);
+            builder.func.create_inst(InstData::Store(value, alloc));
+            builder.vars.insert(param.clone(), alloc);
+            builder.func.var_names_mut().insert(alloc, param.clone());
+        }
+    }
 
     for stmt in func.stmts() {
-        builder.generate(stmt);
+        builder.generate(stmt)?;
     }
 
     let ret = InstData::ReturnVoid;
@@ -468,15 +883,15 @@ pub fn generate_instructions(func: &parser::Func) -> ir::function::Function {
     // Check does function have statements. It is needed in the next check on return.
     if func.stmts().is_empty() {
         builder.func.create_inst(ret);
-        return builder.func;
+        return Ok(builder.func);
     }
 
-    // If in the AST the last statement is not Return than return is implicit and in IR we have it
-    // explicit
+    // If control may fall through the last statement of the AST then return is implicit and in IR
+    // we have it explicit. It also ends the dead code after a return, if there is such code.
     let last = func.stmts().last().unwrap();
-    if !(matches!(last, Node::Return { .. }) || matches!(last, Node::ReturnVoid)) {
+    if !never_falls_through(last) {
         builder.func.create_inst(ret);
     }
 
-    builder.func
+    Ok(builder.func)
 }
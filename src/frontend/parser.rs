@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::collections::HashSet;
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub enum Token {
@@ -52,10 +51,15 @@ pub enum Token {
     Let,
     Mut,
     I64,
+    I32,
+    Bool,
+    As,
 
     // etc
     IntLiteral(i64),
     Id(String),
+    // Loop label 'name, the name is stored without the quote
+    Label(String),
     Eof,
 
     // A whitespace or a tab character
@@ -64,7 +68,8 @@ pub enum Token {
     // A new line
     LineFeed,
 
-    COMMENT,
+    // Text of a line comment after the two slashes
+    COMMENT(String),
 }
 
 use std::fmt;
@@ -99,6 +104,7 @@ impl fmt::Display for Token {
             Token::Eof => write!(f, "Eof"),
             Token::Func => write!(f, "Function"),
             Token::Id(name) => write!(f, "ID<{}>", name),
+            Token::Label(name) => write!(f, "Label<'{}>", name),
 
             Token::True => write!(f, "true"),
             Token::False => write!(f, "false"),
@@ -110,15 +116,52 @@ impl fmt::Display for Token {
             Token::Let => write!(f, "Let"),
             Token::Mut => write!(f, "Mutable"),
             Token::I64 => write!(f, "i64"),
+            Token::I32 => write!(f, "i32"),
+            Token::Bool => write!(f, "bool"),
+            Token::As => write!(f, "As"),
             Token::LineFeed => write!(f, "LineFeed"),
+            Token::COMMENT(text) => write!(f, "Comment<{}>", text),
             _ => std::unreachable!("Got blank or comment token"),
         }
     }
 }
 
+/// Type of a variable, a parameter or a function result
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    I64,
+    I32,
+    Bool,
+}
+
+impl Type {
+    /// Number of bits of an integer type, None for bool
+    pub fn width(self) -> Option<u32> {
+        match self {
+            Type::I64 => Some(64),
+            Type::I32 => Some(32),
+            Type::Bool => None,
+        }
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.width().is_some()
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::I64 => write!(f, "i64"),
+            Type::I32 => write!(f, "i32"),
+            Type::Bool => write!(f, "bool"),
+        }
+    }
+}
+
 impl Token {
     fn should_ignore(&self) -> bool {
-        matches!(self, Token::Blank | Token::LineFeed | Token::COMMENT)
+        matches!(self, Token::Blank | Token::LineFeed | Token::COMMENT(_))
     }
 }
 
@@ -134,12 +177,15 @@ fn build_keywords() -> HashMap<&'static str, (Token, usize)> {
     const FN: &str = "fn";
     const LET: &str = "let";
     const I64: &str = "i64";
+    const I32: &str = "i32";
+    const BOOL: &str = "bool";
     const MUT: &str = "mut";
     const WHILE: &str = "while";
     const BREAK: &str = "break";
     const CONTINUE: &str = "continue";
+    const AS: &str = "as";
 
-    let mut keywords: HashMap<&str, (Token, usize)> = HashMap::with_capacity(12);
+    let mut keywords: HashMap<&str, (Token, usize)> = HashMap::with_capacity(15);
     keywords.insert(RETURN, (Token::Return, RETURN.len()));
     keywords.insert(TRUE, (Token::True, TRUE.len()));
     keywords.insert(FALSE, (Token::False, FALSE.len()));
@@ -148,26 +194,36 @@ fn build_keywords() -> HashMap<&'static str, (Token, usize)> {
     keywords.insert(FN, (Token::Func, FN.len()));
     keywords.insert(LET, (Token::Let, LET.len()));
     keywords.insert(I64, (Token::I64, I64.len()));
+    keywords.insert(I32, (Token::I32, I32.len()));
+    keywords.insert(BOOL, (Token::Bool, BOOL.len()));
     keywords.insert(MUT, (Token::Mut, MUT.len()));
     keywords.insert(WHILE, (Token::While, WHILE.len()));
     keywords.insert(BREAK, (Token::Break, BREAK.len()));
     keywords.insert(CONTINUE, (Token::Continue, CONTINUE.len()));
+    keywords.insert(AS, (Token::As, AS.len()));
 
     keywords
 }
 
 type TokenLen = usize;
 
-fn tokenize_symbols(input: &String) -> Result<Option<(Token, TokenLen)>, String> {
-    if input.len() >= 2 {
-        // Check the symbol has multilength at read-offset
-        let multilength: String = Some(&input[0..2]).unwrap().into();
-        if let Some(t) = tokenize_multisymbols(&multilength) {
+// Error of tokenizing
+enum TokenError {
+    // Character which can't start any token
+    Mark(char),
+    // Integer literal greater than 2^63
+    Overflow,
+}
+
+fn tokenize_symbols(input: &str) -> Result<Option<(Token, TokenLen)>, TokenError> {
+    // Check the symbol has multilength at read-offset
+    if let Some(multilength) = input.get(0..2) {
+        if let Some(t) = tokenize_multisymbols(multilength) {
             return Ok(Some((t, 2)));
         }
     }
 
-    match input.as_bytes()[0] as char {
+    match input.chars().next().unwrap() {
         '+' => Ok(Some((Token::Plus, 1))),
         '-' => Ok(Some((Token::Minus, 1))),
         '*' => Ok(Some((Token::Star, 1))),
@@ -186,29 +242,30 @@ fn tokenize_symbols(input: &String) -> Result<Option<(Token, TokenLen)>, String>
         ' ' => Ok(Some((Token::Blank, count_len(input, |c| c == &' ')))),
         '\n' => Ok(Some((Token::LineFeed, 1))),
         '\t' => Ok(Some((Token::Blank, 1))),
-        '\0' => Ok(Some((Token::Eof, 1))),
-        c => Err(format!("unexpected mark '{}'", c)),
+        c => Err(TokenError::Mark(c)),
     }
 }
 
 fn tokenize_keywords(
     input: &str,
     keywords: &HashMap<&str, (Token, usize)>,
-) -> Result<Option<(Token, TokenLen)>, String> {
-    let length: TokenLen = count_len(input, |c| c.is_digit(10) || c == &'_' || c.is_alphabetic());
+) -> Result<Option<(Token, TokenLen)>, TokenError> {
+    let length: TokenLen = count_len(input, is_identifier_char);
 
     if let Some(t) = keywords.get(&input[0..length]) {
         return Ok(Some((t.0.clone(), t.1)));
     }
 
-    Ok(Some((
-        Token::Id(input.chars().take(length).collect::<String>()),
-        length,
-    )))
+    Ok(Some((Token::Id(input[0..length].to_string()), length)))
+}
+
+/// Identifiers and labels are `[A-Za-z_][A-Za-z0-9_]*`
+fn is_identifier_char(ch: &char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == &'_'
 }
 
 fn is_decimal(ch: char) -> bool {
-    ('0'..='9').contains(&ch)
+    ch.is_ascii_digit()
 }
 
 fn count_len(input: &str, f: fn(ch: &char) -> bool) -> TokenLen {
@@ -229,36 +286,54 @@ fn tokenize_multisymbols(input: &str) -> Option<Token> {
 }
 
 fn tokenize(
-    input: &mut String,
+    input: &str,
     keywords: &HashMap<&str, (Token, usize)>,
-) -> Result<Option<(Token, TokenLen)>, String> {
+) -> Result<Option<(Token, TokenLen)>, TokenError> {
     // return None if can not tokenize
     if input.is_empty() {
         return Ok(None);
     }
 
-    match input.as_bytes()[0] as char {
+    match input.chars().next().unwrap() {
         // keyword and identifier
-        c if c.is_alphabetic() => tokenize_keywords(input, keywords),
+        c if c.is_ascii_alphabetic() || c == '_' => tokenize_keywords(input, keywords),
 
         // integer-literal
+        // 2^63 is allowed only after minus, it is lexed as i64::MIN and checked by the parser
         c if is_decimal(c) => {
             let length: TokenLen = count_len(input, |c| c.is_ascii_digit());
-            Ok(Some((
-                Token::IntLiteral(input[..length].parse::<i64>().unwrap()),
-                length,
-            )))
+            match input[..length].parse::<u64>() {
+                Ok(val) if val <= i64::MIN.unsigned_abs() => {
+                    Ok(Some((Token::IntLiteral(val as i64), length)))
+                }
+                _ => Err(TokenError::Overflow),
+            }
         }
 
         // ignore comment or Token::Slash
         '/' => {
-            if input.as_bytes()[1] as char == '/' {
-                let length: TokenLen = count_len(input, |c| c != &'\n') + 1;
-                return Ok(Some((Token::COMMENT, length)));
+            if input.starts_with("//") {
+                // The comment with the line feed after it (if the comment is not at the end)
+                let text_len: TokenLen = count_len(input, |c| c != &'\n');
+                let length: TokenLen = (text_len + 1).min(input.len());
+                let text = input[2..text_len].to_string();
+                return Ok(Some((Token::COMMENT(text), length)));
             }
             tokenize_symbols(input)
         }
 
+        // loop label
+        '\'' => {
+            let length: TokenLen = count_len(&input[1..], is_identifier_char);
+            if length == 0 {
+                return Err(TokenError::Mark('\''));
+            }
+            Ok(Some((
+                Token::Label(input[1..=length].to_string()),
+                length + 1,
+            )))
+        }
+
         // ignore white-space
         ' ' => Ok(Some((Token::Blank, count_len(input, |c| c == &' ')))),
         // symbol
@@ -266,31 +341,305 @@ fn tokenize(
     }
 }
 
-type ParseResult<T> = Result<T, String>;
+/// Error found during lexing or parsing. Lines are counted from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A character which can't start any token. Columns are counted in characters from 1.
+    UnexpectedMark { mark: char, line: usize, col: usize },
+    /// A token which doesn't fit the grammar at its position
+    UnexpectedToken {
+        expected: String,
+        found: Token,
+        line: usize,
+    },
+    /// Use of or assignment to a variable which is not declared
+    Undeclared(String),
+    /// Call of a function which is neither defined before nor built-in
+    UndefinedFunction(String),
+    /// Call with a wrong number of arguments
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    /// Call of a variadic function, i.e. the built-in print, with fewer arguments than it takes
+    TooFewArguments {
+        name: String,
+        min: usize,
+        got: usize,
+    },
+    /// `return;` in a function with a return type
+    MissingReturnValue { line: usize },
+    /// Return of a value from a function without a return type
+    UnexpectedReturnValue { line: usize },
+    /// `break;` which is not inside of a loop
+    BreakOutsideLoop { line: usize },
+    /// `continue;` which is not inside of a loop
+    ContinueOutsideLoop { line: usize },
+    /// Label of break or continue which doesn't name any of the enclosing loops
+    UndeclaredLabel { label: String, line: usize },
+    /// Integer literal out of the range of i64
+    IntegerOverflow { line: usize },
+    /// `=` instead of `==` in the condition of if or while
+    AssignInCondition { line: usize },
+    /// Argument of a call which type differs from the type of the parameter. Index is counted
+    /// from 0, the message counts the arguments from 1.
+    ArgumentTypeMismatch {
+        name: String,
+        index: usize,
+        expected: Type,
+        found: Type,
+        line: usize,
+    },
+    /// Function, parameter or local variable named as a built-in function
+    ReservedName { name: String, line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedMark { mark, line, .. } => {
+                write!(f, "line {}: unexpected mark '{}'", line, mark)
+            }
+            Self::UnexpectedToken {
+                expected,
+                found,
+                line,
+            } => write!(
+                f,
+                "line {}: expected {} but got '{}'",
+                line, expected, found
+            ),
+            Self::Undeclared(name) => write!(f, "Use of undeclared variable {}", name),
+            Self::UndefinedFunction(name) => write!(f, "No function named {} defined", name),
+            Self::ArityMismatch {
+                name,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Function {} takes {} but {} given",
+                name,
+                arguments(*expected),
+                were_given(*got)
+            ),
+            Self::TooFewArguments { name, min, got } => write!(
+                f,
+                "Function {} takes at least {} but {} given",
+                name,
+                arguments(*min),
+                were_given(*got)
+            ),
+            Self::MissingReturnValue { line } => {
+                write!(f, "line {}: function with a return type returns void", line)
+            }
+            Self::UnexpectedReturnValue { line } => write!(
+                f,
+                "line {}: function without a return type returns a value",
+                line
+            ),
+            Self::BreakOutsideLoop { line } => write!(f, "line {}: break outside of loop", line),
+            Self::ContinueOutsideLoop { line } => {
+                write!(f, "line {}: continue outside of loop", line)
+            }
+            Self::UndeclaredLabel { label, line } => {
+                write!(f, "line {}: use of undeclared label '{}", line, label)
+            }
+            Self::AssignInCondition { line } => write!(
+                f,
+                "line {}: did you mean '=='? assignment is not allowed in a condition",
+                line
+            ),
+            Self::IntegerOverflow { line } => {
+                write!(f, "line {}: integer literal is out of range of i64", line)
+            }
+            Self::ArgumentTypeMismatch {
+                name,
+                index,
+                expected,
+                found,
+                line,
+            } => write!(
+                f,
+                "line {}: argument {} of function {} should be {} but it is {}",
+                line,
+                index + 1,
+                name,
+                expected,
+                found
+            ),
+            Self::ReservedName { name, line } => write!(
+                f,
+                "line {}: {} is the name of a built-in function and can't be redefined",
+                line, name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Number of the arguments with the noun, e.g. "1 argument" or "2 arguments"
+fn arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_string(),
+        _ => format!("{} arguments", count),
+    }
+}
+
+/// Number of the given arguments with the verb, e.g. "1 was" or "2 were"
+fn were_given(count: usize) -> String {
+    match count {
+        1 => "1 was".to_string(),
+        _ => format!("{} were", count),
+    }
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+pub fn lexing<S: AsRef<str>>(input: S) -> ParseResult<Vec<Token>> {
+    Lexer::new(input.as_ref()).collect()
+}
+
+/// Unlike `lexing` keeps the comments, e.g. for a formatter. Returns each token with its line.
+pub fn lexing_with_trivia<S: AsRef<str>>(input: S) -> ParseResult<Vec<(Token, usize)>> {
+    let (tokens, lines) = tokenize_all(input.as_ref(), true)?;
+    Ok(tokens.into_iter().zip(lines).collect())
+}
+
+/// Returns the tokens and the line of each of them, they are parsed by `parse_tokens`.
+pub fn lexing_with_lines(input: &str) -> ParseResult<(Vec<Token>, Vec<usize>)> {
+    tokenize_all(input, false)
+}
+
+fn tokenize_all(input: &str, keep_comments: bool) -> ParseResult<(Vec<Token>, Vec<usize>)> {
+    let mut lexer = Lexer::new(input);
+    lexer.keep_comments = keep_comments;
 
-pub fn lexing(mut input: String) -> ParseResult<Vec<Token>> {
     let mut tokens: Vec<Token> = Vec::with_capacity(2048);
+    let mut lines: Vec<usize> = Vec::with_capacity(2048);
+    while let Some(token) = lexer.next() {
+        tokens.push(token?);
+        lines.push(lexer.line());
+    }
 
-    // build all keywords they used in izber
-    let keywords: HashMap<&str, (Token, usize)> = build_keywords();
+    Ok((tokens, lines))
+}
 
-    // append this_token to tokens while given tokens are valid
-    while let Some((t, idx)) = tokenize(&mut input, &keywords)? {
-        // next point
-        input.drain(..idx);
+/// Lexer which produces the tokens one by one on demand. Blanks, line feeds and comments are
+/// skipped. The last token is always Eof, nothing is produced after it or after an error.
+pub struct Lexer<'a> {
+    input: &'a str,
+    keywords: HashMap<&'static str, (Token, usize)>,
+    keep_comments: bool,
+    // Byte offset of the not yet tokenized rest of the input
+    pos: usize,
+    // Line of the rest of the input and byte offset of its first character
+    rest_line: usize,
+    line_start: usize,
+    // Line of the token produced last
+    token_line: usize,
+    finished: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            keywords: build_keywords(),
+            keep_comments: false,
+            pos: 0,
+            rest_line: 1,
+            line_start: 0,
+            token_line: 1,
+            finished: false,
+        }
+    }
 
-        if t.should_ignore() {
-            continue;
+    /// Line of the token produced last, lines are counted from 1.
+    pub fn line(&self) -> usize {
+        self.token_line
+    }
+
+    fn error(&mut self, err: TokenError) -> ParseError {
+        self.finished = true;
+        match err {
+            TokenError::Mark(mark) => ParseError::UnexpectedMark {
+                mark,
+                line: self.rest_line,
+                col: self.input[self.line_start..self.pos].chars().count() + 1,
+            },
+            TokenError::Overflow => ParseError::IntegerOverflow {
+                line: self.rest_line,
+            },
         }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = ParseResult<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let (t, idx) = match tokenize(&self.input[self.pos..], &self.keywords) {
+                Ok(Some(token)) => token,
+                // The stream of tokens always ends with Eof
+                Ok(None) => (Token::Eof, 0),
+                Err(err) => return Some(Err(self.error(err))),
+            };
+
+            let text = &self.input[self.pos..self.pos + idx];
+            self.token_line = self.rest_line;
+            self.rest_line += text.matches('\n').count();
+            if let Some(offset) = text.rfind('\n') {
+                self.line_start = self.pos + offset + 1;
+            }
+            self.pos += idx;
+
+            if t.should_ignore() && !(self.keep_comments && matches!(t, Token::COMMENT(_))) {
+                continue;
+            }
 
-        tokens.push(t.clone());
-        // if this_token is End-Of-File then we should exit from tokenize
-        if let &Token::Eof = &t {
-            break;
+            self.finished = t == Token::Eof;
+            return Some(Ok(t));
         }
     }
+}
 
-    Ok(tokens)
+/// Formats the message with the line of the source and a caret under the column, like:
+///
+/// ```text
+/// unexpected mark '#'
+///   |
+/// 3 |     a = 1 # 2;
+///   |           ^
+/// ```
+///
+/// Lines and columns are counted from 1, columns in characters. Line 0 has no text and column 0
+/// puts the caret at the start of the line.
+pub fn format_error_at(source: &str, line: usize, col: usize, msg: &str) -> String {
+    let text = line
+        .checked_sub(1)
+        .and_then(|index| source.lines().nth(index))
+        .unwrap_or("");
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+
+    // Keep the tabs so the caret is under the column whatever the tab width is
+    let indent: String = text
+        .chars()
+        .take(col.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!(
+        "{}\n{} |\n{} | {}\n{} | {}^\n",
+        msg, gutter, number, text, gutter, indent
+    )
 }
 
 type Name = String;
@@ -299,13 +648,22 @@ type Expr = Box<Node>;
 type Condition = Box<Node>;
 type BlockNode = Box<Node>;
 type Alter = Option<Box<Node>>;
+// Initializer of a local variable declaration
+type Init = Option<Box<Node>>;
+type Label = Option<Name>;
 type Elements = Box<Vec<Node>>;
+type TypeName = Type;
+// Line of a statement, counted from 1
+type Line = usize;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Node {
     // Unary-operation
     Neg(Child),
 
+    // Conversion of a value to an integer type: `a as i32`
+    Cast(Child, TypeName),
+
     // Numeric literal
     Integer(i64),
 
@@ -336,20 +694,44 @@ pub enum Node {
     Le(Child, Child),
     Ge(Child, Child),
 
-    // Statements
-    Let(Name, Expr),
-    Assign(Name, Expr),
-    If(Condition, BlockNode, Alter),
-    While(Condition, BlockNode),
-    Break,
-    Continue,
-    Block(Elements),
-    ReturnVoid,
-    Return(Expr),
+    // Statements, each one with its line
+    Let(Name, Init, Line),
+    Assign(Name, Expr, Line),
+    If(Condition, BlockNode, Alter, Line),
+    While(Condition, BlockNode, Label, Line),
+    Break(Label, Line),
+    Continue(Label, Line),
+    Block(Elements, Line),
+    ReturnVoid(Line),
+    Return(Expr, Line),
+
+    // Name of calling function, passing arguments and the line if the call is a separate
+    // statement, None if it is a subexpression.
+    Call(Name, Elements, Option<Line>),
+
+    // Reference to a function used as a value, e.g. `let mut f: i64 = foo;`
+    FuncRef(Name),
+    // Call of a function referenced by the value of the expression, e.g. `f()`
+    CallIndirect(Child, Elements, Option<Line>),
+}
 
-    // Name of calling function, passing arguments and is call separate or it is a
-    // subexpression.
-    Call(Name, Elements, bool),
+impl Node {
+    /// Line of a statement, None for an expression
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Node::Let(_, _, line)
+            | Node::Assign(_, _, line)
+            | Node::If(_, _, _, line)
+            | Node::While(_, _, _, line)
+            | Node::Break(_, line)
+            | Node::Continue(_, line)
+            | Node::Block(_, line)
+            | Node::ReturnVoid(line)
+            | Node::Return(_, line) => Some(*line),
+            Node::Call(_, _, line) | Node::CallIndirect(_, _, line) => *line,
+            _ => None,
+        }
+    }
 }
 
 macro_rules! elements_to_string {
@@ -382,19 +764,21 @@ impl fmt::Display for Node {
             Node::Shr(lch, rch) => write!(f, "Shr<{},{}>", lch, rch),
 
             Node::Neg(child) => write!(f, "Neg<{}>", child),
+            Node::Cast(child, ty) => write!(f, "Cast<{}, {}>", child, ty),
 
             Node::True => write!(f, "True"),
             Node::False => write!(f, "False"),
             Node::Integer(val) => write!(f, "Int<{}> ", val),
 
             Node::Id(name) => write!(f, "Id<{}>", name),
-            Node::ReturnVoid => write!(f, "ReturnVoid"),
-            Node::Return(expr) => write!(f, "Return({})", expr),
+            Node::ReturnVoid(_) => write!(f, "ReturnVoid"),
+            Node::Return(expr, _) => write!(f, "Return({})", expr),
 
-            Node::Let(name, expr) => write!(f, "Let {} = {}", name, expr),
-            Node::Assign(id, expr) => write!(f, "Assign<{}>({})", id, expr),
+            Node::Let(name, Some(expr), _) => write!(f, "Let {} = {}", name, expr),
+            Node::Let(name, None, _) => write!(f, "Let {}", name),
+            Node::Assign(id, expr, _) => write!(f, "Assign<{}>({})", id, expr),
 
-            Node::Block(stmts) => {
+            Node::Block(stmts, _) => {
                 let elements = elements_to_string!(stmts);
                 write!(f, "Block with {} elements: {}", stmts.len(), elements)
             }
@@ -402,14 +786,28 @@ impl fmt::Display for Node {
                 let arguments = elements_to_string!(args);
                 write!(f, "Call {}, args: {}", id, arguments)
             }
+            Node::FuncRef(name) => write!(f, "FuncRef<{}>", name),
+            Node::CallIndirect(callee, args, _) => {
+                let arguments = elements_to_string!(args);
+                write!(f, "CallIndirect {}, args: {}", callee, arguments)
+            }
 
-            Node::While(cond, stmts) => {
+            Node::While(cond, stmts, label, _) => {
+                if let Some(label) = label {
+                    write!(f, "'{}: ", label)?;
+                }
                 write!(f, "While {}:\n\t\t{}", cond, (*stmts))
             }
-            Node::Break => write!(f, "Break"),
-            Node::Continue => write!(f, "Continue"),
-
-            Node::If(cond, stmts, alter) => match alter {
+            Node::Break(None, _) => write!(f, "Break"),
+            Node::Break(Some(label), _) => write!(f, "Break '{}", label),
+            Node::Continue(None, _) => write!(f, "Continue"),
+            Node::Continue(Some(label), _) => write!(f, "Continue '{}", label),
+
+            Node::If(cond, stmts, alter, _) => match alter {
+                // `else if` is rendered as a flat chain instead of an If nested into Else
+                Some(alt) if matches!(**alt, Node::If(..)) => {
+                    write!(f, "IF<{},{}> ELSE {}", cond, stmts, alt)
+                }
                 Some(alt) => write!(f, "IF<{},{}> ELSE<{}>", cond, stmts, alt),
                 None => write!(f, "IF<{},{}>", cond, stmts),
             },
@@ -422,10 +820,13 @@ pub struct Func {
     name: String,
     stmts: Vec<Node>,
     params: Vec<String>,
+    param_types: Vec<Type>,
+    return_type: Option<Type>,
+    var_types: HashMap<String, Type>,
 }
 
 impl Func {
-    pub fn name(&self) -> &String {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
@@ -436,6 +837,23 @@ impl Func {
     pub fn stmts(&self) -> &[Node] {
         &self.stmts
     }
+
+    pub fn param_types(&self) -> &[Type] {
+        &self.param_types
+    }
+
+    pub fn has_return_type(&self) -> bool {
+        self.return_type.is_some()
+    }
+
+    pub fn return_type(&self) -> Option<Type> {
+        self.return_type
+    }
+
+    /// Types of the parameters and the local variables
+    pub fn var_types(&self) -> &HashMap<String, Type> {
+        &self.var_types
+    }
 }
 
 pub fn dump_ast(funcs: &[Func]) {
@@ -451,29 +869,47 @@ pub fn dump_ast(funcs: &[Func]) {
 /// Field tokens is written after lexing one time and is never rewritten, only read.
 /// The source language is only able to have top level variable declarations and no ones in
 /// the inner scopes. Therefore we use cur_variables set for all of the local variables and the
-/// parameters of a function.
+/// parameters of a function with their types.
 struct Parser {
     tokens: Vec<Token>,
+    // Line of each token
+    lines: Vec<usize>,
     // Index of current token in vector of tokens
     cur: usize,
     // Index of the next one
     next: usize,
 
     funcs: Vec<Func>,
-    cur_variables: HashSet<String>,
+    cur_variables: HashMap<String, Type>,
     // Does current function have a return type
     return_type: bool,
+    // Name of the current function, it can be referenced in its body
+    cur_func: String,
+    // Labels of the loops the current statement is nested in, from the outermost one
+    loop_labels: Vec<Option<String>>,
+
+    // In the recovery mode an erroneous statement is recorded to errors and skipped
+    recover: bool,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Parser {
+    fn new(tokens: Vec<Token>, lines: Vec<usize>) -> Parser {
+        debug_assert_eq!(tokens.last(), Some(&Token::Eof));
+        debug_assert_eq!(tokens.len(), lines.len());
+
         Parser {
             tokens,
+            lines,
             funcs: Vec::with_capacity(100),
-            cur_variables: HashSet::new(),
+            cur_variables: HashMap::new(),
             return_type: false,
+            cur_func: String::new(),
+            loop_labels: Vec::new(),
             cur: 0,
             next: 1,
+            recover: false,
+            errors: Vec::new(),
         }
     }
 
@@ -484,19 +920,23 @@ impl Parser {
         loop {
             let t: &Token = self.cur_token();
 
-            match t {
-                &Token::Func => {
+            match *t {
+                Token::Func => {
                     let cur = self.parse_func()?;
                     self.funcs.push(cur);
                 }
-                _ => break,
+                Token::Eof => break,
+                // Only functions are allowed at the top level
+                _ => return Err(self.unexpected(&Token::Func.to_string())),
             }
         }
 
         Ok(())
     }
 
+    /// A statement gets the line of its first token
     fn stmt(&mut self) -> ParseResult<Node> {
+        let line = self.line();
         let t: Token = self.get_token();
         match t {
             Token::Return => self.parse_return(),
@@ -506,50 +946,75 @@ impl Parser {
                 // or opening parenthesis.
 
                 if *self.next_token() == Token::Assign {
-                    if !self.cur_variables.contains(&name) {
-                        return Err(format!("Assign to undeclared variable {}", name));
+                    if !self.cur_variables.contains_key(&name) {
+                        return Err(ParseError::Undeclared(name));
                     }
-                    return self.parse_assign();
+                    return self.parse_assign(line);
                 }
 
                 if *self.next_token() != Token::LParent {
-                    return Err("Undefined token after id".to_string());
+                    return Err(ParseError::UnexpectedToken {
+                        expected: format!("'=' or '(' after identifier {}", name),
+                        found: self.next_token().clone(),
+                        line: self.lines[self.next],
+                    });
                 }
 
-                self.parse_call(name)
+                self.parse_call(name, line)
             }
 
-            Token::LBrace => self.parse_block(),
+            Token::LBrace => self.parse_block(line),
 
-            Token::While => self.parse_while(),
+            Token::While => self.parse_while(None, line),
+            Token::Label(label) => {
+                self.go_next_token();
+                self.expect(&Token::Colon)?;
+                if self.cur_token() != &Token::While {
+                    return Err(self.unexpected("while after loop label"));
+                }
+                self.parse_while(Some(label), line)
+            }
             Token::Break => {
+                if self.loop_labels.is_empty() {
+                    return Err(ParseError::BreakOutsideLoop { line: self.line() });
+                }
                 self.go_next_token();
+                let label = self.loop_label()?;
                 self.expect(&Token::Semi)?;
-                Ok(Node::Break)
+                Ok(Node::Break(label, line))
             }
             Token::Continue => {
+                if self.loop_labels.is_empty() {
+                    return Err(ParseError::ContinueOutsideLoop { line: self.line() });
+                }
                 self.go_next_token();
+                let label = self.loop_label()?;
                 self.expect(&Token::Semi)?;
-                Ok(Node::Continue)
+                Ok(Node::Continue(label, line))
             }
 
-            Token::If => self.parse_if(),
-            _ => Err(format!("statement can't start with '{}'", t)),
+            Token::If => self.parse_if(line),
+            _ => Err(self.unexpected("statement")),
         }
     }
 
     fn parse_func(&mut self) -> ParseResult<Func> {
         self.expect(&Token::Func)?;
+        let line = self.line();
         let func_name: String = self.consume_id()?;
+        Self::check_not_builtin(&func_name, line)?;
+        self.cur_func = func_name.clone();
         self.expect(&Token::LParent)?;
 
         // Parse function parameter declarations, add parameter names to cur_variables.
         let mut func_params = Vec::new();
-        self.cur_variables = HashSet::new();
+        let mut param_types = Vec::new();
+        self.cur_variables = HashMap::new();
         while !self.consume(&Token::RParent) {
-            let param_name = self.define_param()?;
+            let (param_name, param_type) = self.define_param()?;
             func_params.push(param_name.clone());
-            self.cur_variables.insert(param_name.clone());
+            param_types.push(param_type);
+            self.cur_variables.insert(param_name, param_type);
 
             if !self.consume(&Token::Comma) {
                 self.expect(&Token::RParent)?;
@@ -557,149 +1022,209 @@ impl Parser {
             }
         }
 
-        self.return_type = false;
-        if self.consume(&Token::Arrow) {
-            self.consume_typename()?;
-            self.return_type = true;
-        }
+        self.loop_labels.clear();
+        let return_type = if self.consume(&Token::Arrow) {
+            Some(self.consume_typename()?)
+        } else {
+            None
+        };
+        self.return_type = return_type.is_some();
 
         self.expect(&Token::LBrace)?;
 
         let mut func_stmts = Vec::new();
         // Parse local variable declarations.
         while self.cur_token() == &Token::Let {
-            let let_ = self.parse_let()?;
-            if let Node::Let(name, _expr) = let_.clone() {
-                self.cur_variables.insert(name.clone());
-            } else {
-                std::unreachable!();
-            }
-            func_stmts.push(let_);
+            let lets = self.parse_let()?;
+            func_stmts.extend(lets);
         }
 
         // Parse function statements including blocks.
-        while !self.consume(&Token::RBrace) {
-            let st: Node = self.stmt()?;
-            func_stmts.push(st);
-        }
+        func_stmts.extend(self.stmts_until_rbrace(self.return_type)?);
 
         Ok(Func {
             name: func_name,
             params: func_params,
+            param_types,
             stmts: func_stmts,
+            return_type,
+            var_types: self.cur_variables.clone(),
         })
     }
 
-    fn define_param(&mut self) -> ParseResult<String> {
+    fn define_param(&mut self) -> ParseResult<(String, Type)> {
+        let line = self.line();
         let param_name: String = self.consume_id()?;
+        Self::check_not_builtin(&param_name, line)?;
         self.consume(&Token::Colon);
-        self.consume_typename()?;
+        let param_type = self.consume_typename()?;
 
-        Ok(param_name)
+        Ok((param_name, param_type))
     }
 
-    fn parse_while(&mut self) -> ParseResult<Node> {
+    fn parse_while(&mut self, label: Option<String>, line: usize) -> ParseResult<Node> {
         self.expect(&Token::While)?;
+        let cond: Node = self.condition()?;
+
+        self.loop_labels.push(label.clone());
+        let stmt = self.stmt();
+        self.loop_labels.pop();
+
+        Ok(Node::While(Box::new(cond), Box::new(stmt?), label, line))
+    }
+
+    /// Parse optional label after break or continue, it must name one of the enclosing loops.
+    fn loop_label(&mut self) -> ParseResult<Option<String>> {
+        let label = match self.cur_token() {
+            Token::Label(label) => label.clone(),
+            _ => return Ok(None),
+        };
+
+        if !self.loop_labels.iter().any(|l| l.as_ref() == Some(&label)) {
+            return Err(ParseError::UndeclaredLabel {
+                label,
+                line: self.line(),
+            });
+        }
+        self.go_next_token();
+
+        Ok(Some(label))
+    }
+
+    /// Parse the condition of if or while in parentheses.
+    fn condition(&mut self) -> ParseResult<Node> {
         self.expect(&Token::LParent)?;
         let cond: Node = self.expr()?;
+        if self.check(&Token::Assign) {
+            return Err(ParseError::AssignInCondition { line: self.line() });
+        }
         self.expect(&Token::RParent)?;
-        let stmt: Node = self.stmt()?;
-        Ok(Node::While(Box::new(cond), Box::new(stmt)))
+
+        Ok(cond)
     }
 
-    fn parse_block(&mut self) -> ParseResult<Node> {
+    fn parse_block(&mut self, line: usize) -> ParseResult<Node> {
         let stmts: Vec<Node> = self.compound_stmt()?;
-        Ok(Node::Block(Box::new(stmts)))
+        Ok(Node::Block(Box::new(stmts), line))
     }
 
-    fn parse_if(&mut self) -> ParseResult<Node> {
+    fn parse_if(&mut self, line: usize) -> ParseResult<Node> {
         self.expect(&Token::If)?;
-        self.expect(&Token::LParent)?;
-        let cond: Node = self.expr()?;
-        self.expect(&Token::RParent)?;
+        let cond: Node = self.condition()?;
 
         let stmt: Node = self.stmt()?;
         if !self.consume(&Token::Else) {
-            return Ok(Node::If(Box::new(cond), Box::new(stmt), None));
+            return Ok(Node::If(Box::new(cond), Box::new(stmt), None, line));
         }
         Ok(Node::If(
             Box::new(cond),
             Box::new(stmt),
             Some(Box::new(self.stmt()?)),
+            line,
         ))
     }
 
-    fn parse_let(&mut self) -> ParseResult<Node> {
+    /// Parses `let mut a: i64 = 0, b: i64 = 1;` into a Let node per declared variable. Every
+    /// variable is visible in the initializers which follow it.
+    fn parse_let(&mut self) -> ParseResult<Vec<Node>> {
         self.expect(&Token::Let)?;
         self.expect(&Token::Mut)?;
-        let id_name: String = self.consume_id()?;
-        self.expect(&Token::Colon)?;
-        self.consume_typename()?;
-        self.expect(&Token::Assign)?;
-        let expr = self.expr()?;
+
+        let mut lets = Vec::new();
+        loop {
+            let line = self.line();
+            let id_name: String = self.consume_id()?;
+            Self::check_not_builtin(&id_name, line)?;
+            self.expect(&Token::Colon)?;
+            let var_type = self.consume_typename()?;
+            let init = if self.consume(&Token::Assign) {
+                Some(Box::new(self.expr()?))
+            } else {
+                None
+            };
+
+            self.cur_variables.insert(id_name.clone(), var_type);
+            lets.push(Node::Let(id_name, init, line));
+
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
         self.expect(&Token::Semi)?;
 
-        Ok(Node::Let(id_name, Box::new(expr)))
+        Ok(lets)
     }
 
     fn parse_return(&mut self) -> ParseResult<Node> {
+        let line = self.line();
         self.expect(&Token::Return)?;
         if let Token::Semi = self.cur_token() {
             // `let` expressions in this position are experimental
             // cargo 1.54.0 (5ae8d74b3 2021-06-22)
             if self.return_type {
-                return Err("Function with a returning type returns void".to_string());
+                return Err(ParseError::MissingReturnValue { line });
             }
 
             self.go_next_token();
-            return Ok(Node::ReturnVoid);
+            return Ok(Node::ReturnVoid(line));
         }
 
         let expr: Node = self.expr()?;
         if !self.return_type {
-            return Err(format!(
-                "Function with no return type returns value: {}",
-                expr
-            ));
+            return Err(ParseError::UnexpectedReturnValue { line });
         }
 
         self.expect(&Token::Semi)?;
-        Ok(Node::Return(Box::new(expr)))
+        Ok(Node::Return(Box::new(expr), line))
     }
 
-    fn parse_call(&mut self, name: String) -> ParseResult<Node> {
+    fn parse_call(&mut self, name: String, line: usize) -> ParseResult<Node> {
         let id_name: String = self.consume_id()?;
         debug_assert!(id_name == name);
+        let args = self.parse_arguments()?;
+        let call = self.call_node(name, args, Some(line))?;
+
+        self.expect(&Token::Semi)?;
+        Ok(call)
+    }
+
+    /// A call by the name of a variable is a call of the function referenced by its value.
+    /// The number of the arguments of such call is not checked. `line` is set for a call which is
+    /// a statement.
+    fn call_node(&self, name: String, args: Vec<Node>, line: Option<usize>) -> ParseResult<Node> {
+        if self.cur_variables.contains_key(&name) {
+            let callee = Box::new(Node::Id(name));
+            return Ok(Node::CallIndirect(callee, Box::new(args), line));
+        }
+
+        self.check_call(&name, &args)?;
+        Ok(Node::Call(name, Box::new(args), line))
+    }
+
+    /// Parse arguments of a call in parentheses. Arguments are separated by commas, a trailing
+    /// comma after the last argument is allowed: `f(1, 2,)`.
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Node>> {
         self.expect(&Token::LParent)?;
 
         let mut args: Vec<Node> = Vec::new();
-        loop {
-            match self.cur_token() {
-                Token::Comma => self.go_next_token(),
-                Token::RParent => {
-                    self.go_next_token();
-                    break;
-                }
-                _ => {
-                    let expr = self.expr()?;
-                    args.push(expr);
-                }
+        while !self.consume(&Token::RParent) {
+            args.push(self.expr()?);
+            if !self.consume(&Token::Comma) {
+                self.expect(&Token::RParent)?;
+                break;
             }
         }
 
-        self.check_call(&name, args.len())?;
-
-        self.expect(&Token::Semi)?;
-        Ok(Node::Call(name, Box::new(args), false))
+        Ok(args)
     }
 
-    fn parse_assign(&mut self) -> ParseResult<Node> {
+    fn parse_assign(&mut self, line: usize) -> ParseResult<Node> {
         let id_name: String = self.consume_id()?;
         self.expect(&Token::Assign)?;
         let expr: Node = self.expr()?;
         self.expect(&Token::Semi)?;
 
-        Ok(Node::Assign(id_name, Box::new(expr)))
+        Ok(Node::Assign(id_name, Box::new(expr), line))
     }
 
     fn expr(&mut self) -> ParseResult<Node> {
@@ -709,7 +1234,7 @@ impl Parser {
     fn equal(&mut self) -> ParseResult<Node> {
         let mut lhs: Node = self.relation()?;
 
-        while self.check_vec(vec![Token::Eq, Token::Ne]) {
+        while self.check_vec(&[Token::Eq, Token::Ne]) {
             let op: Token = self.get_token();
             self.go_next_token();
 
@@ -726,18 +1251,18 @@ impl Parser {
     fn relation(&mut self) -> ParseResult<Node> {
         let mut lhs: Node = self.shift()?;
 
-        while self.check_vec(vec![Token::Lt, Token::Gt, Token::Le, Token::Ge]) {
+        while self.check_vec(&[Token::Lt, Token::Gt, Token::Le, Token::Ge]) {
             let op: Token = self.get_token();
             self.go_next_token();
 
             if let &Token::Lt = &op {
-                lhs = Node::Lt(Box::new(lhs), Box::new(self.relation()?));
+                lhs = Node::Lt(Box::new(lhs), Box::new(self.shift()?));
             } else if let &Token::Gt = &op {
-                lhs = Node::Gt(Box::new(lhs), Box::new(self.relation()?));
+                lhs = Node::Gt(Box::new(lhs), Box::new(self.shift()?));
             } else if let &Token::Le = &op {
-                lhs = Node::Le(Box::new(lhs), Box::new(self.relation()?));
+                lhs = Node::Le(Box::new(lhs), Box::new(self.shift()?));
             } else if let &Token::Ge = &op {
-                lhs = Node::Ge(Box::new(lhs), Box::new(self.relation()?));
+                lhs = Node::Ge(Box::new(lhs), Box::new(self.shift()?));
             }
         }
 
@@ -765,7 +1290,7 @@ impl Parser {
     fn add_sub(&mut self) -> ParseResult<Node> {
         let mut lhs: Node = self.mul_div()?;
 
-        while self.check_vec(vec![Token::Plus, Token::Minus]) {
+        while self.check_vec(&[Token::Plus, Token::Minus]) {
             let op: Token = self.get_token();
             self.go_next_token();
             if let Token::Plus = op {
@@ -779,28 +1304,51 @@ impl Parser {
     }
 
     fn mul_div(&mut self) -> ParseResult<Node> {
-        let mut lhs: Node = self.unary()?;
+        let mut lhs: Node = self.cast()?;
 
-        while self.check_vec(vec![Token::Star, Token::Slash, Token::Percent]) {
+        while self.check_vec(&[Token::Star, Token::Slash, Token::Percent]) {
             let op: Token = self.get_token();
             self.go_next_token();
             if let Token::Star = op {
-                lhs = Node::Mul(Box::new(lhs), Box::new(self.unary()?));
+                lhs = Node::Mul(Box::new(lhs), Box::new(self.cast()?));
             } else if let Token::Slash = op {
-                lhs = Node::Div(Box::new(lhs), Box::new(self.unary()?));
+                lhs = Node::Div(Box::new(lhs), Box::new(self.cast()?));
             } else if let Token::Percent = op {
-                lhs = Node::Mod(Box::new(lhs), Box::new(self.unary()?));
+                lhs = Node::Mod(Box::new(lhs), Box::new(self.cast()?));
             }
         }
 
         Ok(lhs)
     }
 
+    /// `as` binds tighter than the binary operations but looser than the unary minus, so
+    /// `-a as i32` is `(-a) as i32`. A value can be cast to an integer type only.
+    fn cast(&mut self) -> ParseResult<Node> {
+        let mut lhs: Node = self.unary()?;
+
+        while self.consume(&Token::As) {
+            if *self.cur_token() == Token::Bool {
+                return Err(self.unexpected("integer type"));
+            }
+            lhs = Node::Cast(Box::new(lhs), self.consume_typename()?);
+        }
+
+        Ok(lhs)
+    }
+
     fn unary(&mut self) -> ParseResult<Node> {
         let t: Token = self.get_token();
         match t {
             Token::Minus => {
                 self.go_next_token();
+
+                // Minus right before an integer literal makes a negative constant. The only
+                // negative literal from the lexer is 2^63 which is negated to itself.
+                if let Token::IntLiteral(val) = *self.cur_token() {
+                    self.go_next_token();
+                    return Ok(Node::Integer(val.wrapping_neg()));
+                }
+
                 Ok(Node::Neg(Box::new(self.unary()?)))
             }
             _ => self.term(),
@@ -819,6 +1367,9 @@ impl Parser {
             }
 
             Token::IntLiteral(val) => {
+                if val < 0 {
+                    return Err(ParseError::IntegerOverflow { line: self.line() });
+                }
                 self.go_next_token();
                 Ok(Node::Integer(val))
             }
@@ -838,56 +1389,96 @@ impl Parser {
                 match t {
                     // Call case
                     Token::LParent => {
-                        // TODO: refactor using call of `parse_call`
-                        self.expect(&Token::LParent)?;
-                        let mut args: Vec<Node> = Vec::new();
-
-                        while !self.consume(&Token::RParent) {
-                            args.push(self.expr()?);
-                            if !self.consume(&Token::Comma) {
-                                self.expect(&Token::RParent)?;
-                                break;
-                            }
-                        }
-
-                        self.check_call(&name, args.len())?;
-
-                        Ok(Node::Call(name, Box::new(args), true))
+                        let args = self.parse_arguments()?;
+                        self.call_node(name, args, None)
                     }
 
                     _ => {
-                        if self.cur_variables.contains(&name) {
+                        if self.cur_variables.contains_key(&name) {
                             Ok(Node::Id(name))
+                        } else if self.is_function(&name) {
+                            Ok(Node::FuncRef(name))
                         } else {
-                            return Err(format!("Use of undeclared variable {}", name));
+                            Err(ParseError::Undeclared(name))
                         }
                     }
                 }
             }
 
-            _ => Err(format!("term can't start with '{}'", t)),
+            _ => Err(self.unexpected("term")),
         }
     }
 
     /// Parse block of statements (begining with opening curly brace and ending with the closing one)
     /// and return vector of the nodes
     fn compound_stmt(&mut self) -> ParseResult<Vec<Node>> {
-        let mut stmts: Vec<Node> = Vec::new();
         self.expect(&Token::LBrace)?;
+        self.stmts_until_rbrace(false)
+    }
+
+    /// Parse statements up to the closing curly brace inclusive. In the recovery mode an erroneous
+    /// statement is recorded and skipped, parsing continues from the next statement. If `tail` is
+    /// set the expression without a semicolon before the brace is parsed as returned: `{ a + 1 }`.
+    fn stmts_until_rbrace(&mut self, tail: bool) -> ParseResult<Vec<Node>> {
+        let mut stmts: Vec<Node> = Vec::new();
         while !self.consume(&Token::RBrace) {
-            let st: Node = self.stmt()?;
-            stmts.push(st);
+            if tail {
+                let line = self.line();
+                if let Some(expr) = self.tail_expr() {
+                    stmts.push(Node::Return(Box::new(expr), line));
+                    continue;
+                }
+            }
+
+            match self.stmt() {
+                Ok(st) => stmts.push(st),
+                Err(err) if self.recover && *self.cur_token() != Token::Eof => {
+                    self.errors.push(err);
+                    self.synchronize();
+                }
+                Err(err) => return Err(err),
+            }
         }
         Ok(stmts)
     }
 
+    /// Parses the expression if the closing curly brace follows it. Otherwise goes back to the
+    /// current token and returns None, so it is parsed as a statement.
+    fn tail_expr(&mut self) -> Option<Node> {
+        let (cur, next) = (self.cur, self.next);
+        if let Ok(expr) = self.expr() {
+            if self.check(&Token::RBrace) {
+                return Some(expr);
+            }
+        }
+
+        self.cur = cur;
+        self.next = next;
+        None
+    }
+
+    /// Skips tokens up to the end of the current statement: after the semicolon or before the
+    /// closing curly brace.
+    fn synchronize(&mut self) {
+        loop {
+            match self.cur_token() {
+                Token::Semi => {
+                    self.go_next_token();
+                    return;
+                }
+                Token::RBrace | Token::Eof => return,
+                _ => self.go_next_token(),
+            }
+        }
+    }
+
     fn expect(&mut self, t: &Token) -> ParseResult<()> {
         let cur: &Token = self.cur_token();
         if t == cur {
             self.go_next_token();
             return Ok(());
         }
-        Err(format!("expected {} but got '{}'", t, cur))
+        Err(self.unexpected(&t.to_string()))
     }
 
     fn consume(&mut self, t: &Token) -> bool {
@@ -900,15 +1491,23 @@ impl Parser {
         }
     }
 
-    fn consume_typename(&mut self) -> ParseResult<Token> {
+    fn consume_typename(&mut self) -> ParseResult<Type> {
         let t: Token = self.get_token();
         match t {
             Token::I64 => {
                 self.go_next_token();
-                Ok(Token::I64)
+                Ok(Type::I64)
+            }
+            Token::I32 => {
+                self.go_next_token();
+                Ok(Type::I32)
+            }
+            Token::Bool => {
+                self.go_next_token();
+                Ok(Type::Bool)
             }
 
-            _ => Err(format!("got {}, it's not a type name ", t)),
+            _ => Err(self.unexpected("type name")),
         }
     }
 
@@ -918,38 +1517,48 @@ impl Parser {
             self.go_next_token();
             Ok(name)
         } else {
-            Err(format!("expected identifier but got '{}'", t))
+            Err(self.unexpected("identifier"))
         }
     }
 
-    fn check_vec(&self, tks: Vec<Token>) -> bool {
+    /// Error on the current token which is not the expected one
+    fn unexpected(&self, expected: &str) -> ParseError {
+        ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: self.get_token(),
+            line: self.line(),
+        }
+    }
+
+    /// Line of the current token
+    fn line(&self) -> usize {
+        self.lines[self.cur]
+    }
+
+    fn check_vec(&self, tks: &[Token]) -> bool {
         tks.iter().any(|t| t == self.cur_token())
     }
 
     fn get_token(&self) -> Token {
-        if self.cur >= self.tokens.len() {
-            return Token::Eof;
-        }
-        self.tokens[self.cur].clone()
+        self.cur_token().clone()
     }
 
     fn cur_token(&self) -> &Token {
-        if self.cur >= self.tokens.len() {
-            return &Token::Eof;
-        }
         &self.tokens[self.cur]
     }
 
+    /// Token after the current one, the current one must not be the final Eof.
     fn next_token(&self) -> &Token {
-        if self.cur >= self.tokens.len() {
-            return &Token::Eof;
-        }
         &self.tokens[self.next]
     }
 
+    /// Moves to the next token. The last token is always Eof, once it is reached the parser
+    /// stays at it.
     fn go_next_token(&mut self) {
-        self.cur += 1;
-        self.next += 1;
+        if self.cur + 1 < self.tokens.len() {
+            self.cur += 1;
+            self.next += 1;
+        }
     }
 
     fn check(&self, t: &Token) -> bool {
@@ -959,44 +1568,158 @@ impl Parser {
         false
     }
 
-    fn check_call(&self, name: &str, args_len: usize) -> Result<(), String> {
+    /// The current function or one defined before it, built-in functions can't be referenced
+    fn is_function(&self, name: &str) -> bool {
+        self.cur_func == name || self.funcs.iter().any(|func| func.name == name)
+    }
+
+    /// Names of the built-in functions, they can't be used as the names of the functions and the
+    /// variables
+    const BUILTINS: [&'static str; 2] = ["print", "assert"];
+
+    fn check_not_builtin(name: &str, line: usize) -> ParseResult<()> {
+        if Self::BUILTINS.contains(&name) {
+            return Err(ParseError::ReservedName {
+                name: name.to_string(),
+                line,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the number of the arguments and the types of the ones which are known.
+    fn check_call(&self, name: &str, args: &[Node]) -> ParseResult<()> {
+        let args_len = args.len();
         for func in &self.funcs {
             if func.name.as_str() != name {
                 continue;
             }
 
             if func.params.len() != args_len {
-                return Err(format!(
-                    "Function {} takes {} arguments but {} was given",
-                    name,
-                    func.params.len(),
-                    args_len
-                ));
-            } else {
-                return Ok(());
+                return Err(ParseError::ArityMismatch {
+                    name: name.to_string(),
+                    expected: func.params.len(),
+                    got: args_len,
+                });
             }
+
+            for (index, (arg, expected)) in args.iter().zip(&func.param_types).enumerate() {
+                match self.type_of(arg) {
+                    // An integer of another width is converted to the type of the parameter
+                    Some(found) if found.is_integer() && expected.is_integer() => (),
+                    Some(found) if found != *expected => {
+                        return Err(ParseError::ArgumentTypeMismatch {
+                            name: name.to_string(),
+                            index,
+                            expected: *expected,
+                            found,
+                            line: self.line(),
+                        })
+                    }
+                    _ => (),
+                }
+            }
+
+            return Ok(());
         }
 
-        const PRINT_ARGS_LEN: usize = 1;
-        // Should be a built-in function
+        // Should be a built-in function. Assert takes exactly one condition.
+        if name == "assert" && args_len != 1 {
+            return Err(ParseError::ArityMismatch {
+                name: name.to_string(),
+                expected: 1,
+                got: args_len,
+            });
+        }
+        if name == "assert" {
+            return Ok(());
+        }
+
+        // Print takes one or more arguments.
         if name == "print" {
-            if args_len == PRINT_ARGS_LEN {
+            if args_len > 0 {
                 return Ok(());
             } else {
-                return Err(format!(
-                    "Function print takes 1 argument but {} was given",
-                    args_len
-                ));
+                return Err(ParseError::TooFewArguments {
+                    name: name.to_string(),
+                    min: 1,
+                    got: args_len,
+                });
             }
         }
 
-        Err(format!("No function named {} defined", name))
+        Err(ParseError::UndefinedFunction(name.to_string()))
+    }
+
+    /// Infers the type of an expression. None is returned for a call of a function without a
+    /// return type.
+    fn type_of(&self, expr: &Node) -> Option<Type> {
+        match expr {
+            Node::Integer(_)
+            | Node::Neg(_)
+            | Node::Add(_, _)
+            | Node::Sub(_, _)
+            | Node::Mul(_, _)
+            | Node::Div(_, _)
+            | Node::Mod(_, _)
+            | Node::Shl(_, _)
+            | Node::Shr(_, _) => Some(Type::I64),
+
+            Node::True
+            | Node::False
+            | Node::Eq(_, _)
+            | Node::Ne(_, _)
+            | Node::Lt(_, _)
+            | Node::Gt(_, _)
+            | Node::Le(_, _)
+            | Node::Ge(_, _) => Some(Type::Bool),
+
+            Node::Id(name) => self.cur_variables.get(name).copied(),
+            Node::Cast(_, ty) => Some(*ty),
+            // Function is referenced by its index
+            Node::FuncRef(_) => Some(Type::I64),
+            // Built-in print returns the last printed value
+            Node::Call(name, args, _) if name == "print" => self.type_of(args.last()?),
+            Node::Call(name, _, _) => self
+                .funcs
+                .iter()
+                .find(|func| &func.name == name)
+                .and_then(|func| func.return_type),
+
+            _ => None,
+        }
     }
 }
 
-pub fn parse(source: String) -> ParseResult<Vec<Func>> {
-    let tokens = lexing(source)?;
-    let mut parser: Parser = Parser::new(tokens);
+/// Parses the source into the functions. Malformed input gives an error, it never panics.
+pub fn parse<S: AsRef<str>>(source: S) -> ParseResult<Vec<Func>> {
+    let (tokens, lines) = lexing_with_lines(source.as_ref())?;
+    parse_tokens(tokens, lines)
+}
+
+/// Parses the result of `lexing_with_lines`, e.g. when lexing and parsing are timed separately.
+pub fn parse_tokens(tokens: Vec<Token>, lines: Vec<usize>) -> ParseResult<Vec<Func>> {
+    let mut parser: Parser = Parser::new(tokens, lines);
     parser.top_level()?;
     Ok(parser.funcs)
 }
+
+/// Unlike `parse` doesn't stop at the first error in a statement but skips the statement and
+/// continues, so all of the found errors are returned.
+pub fn parse_all<S: AsRef<str>>(source: S) -> Result<Vec<Func>, Vec<ParseError>> {
+    let (tokens, lines) = lexing_with_lines(source.as_ref()).map_err(|err| vec![err])?;
+    let mut parser: Parser = Parser::new(tokens, lines);
+    parser.recover = true;
+
+    let result = parser.top_level();
+    let mut errors = std::mem::take(&mut parser.errors);
+    if let Err(err) = result {
+        errors.push(err);
+    }
+
+    if errors.is_empty() {
+        Ok(parser.funcs)
+    } else {
+        Err(errors)
+    }
+}
@@ -1,32 +1,231 @@
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
+use std::time::{Duration, Instant};
 
-use ctl::frontend::inst_builder::generate_instructions;
-use ctl::frontend::parser::parse;
+use ctl::frontend::inst_builder::generate_module;
+use ctl::frontend::parser::{
+    dump_ast, format_error_at, lexing_with_lines, parse_tokens, ParseError,
+};
 use ctl::optimizer::ir_builder::build_intermediate_representation;
+use ctl::optimizer::pipeline::{run_passes, OptLevel};
+use ctl::optimizer::unused_locals::find_unused_locals;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("Compiler needs at least 1 argument - source file name");
-        return Ok(());
+/// Phases of the compilation timed by --time-passes
+#[derive(Clone, Copy)]
+enum Phase {
+    Lexing,
+    Parsing,
+    IrGeneration,
+    CfgConstruction,
+    Checks,
+    UnusedLocals,
+    Optimization,
+}
+
+impl Phase {
+    const ALL: [Phase; 7] = [
+        Phase::Lexing,
+        Phase::Parsing,
+        Phase::IrGeneration,
+        Phase::CfgConstruction,
+        Phase::Checks,
+        Phase::UnusedLocals,
+        Phase::Optimization,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Lexing => "lexing",
+            Phase::Parsing => "parsing",
+            Phase::IrGeneration => "IR generation",
+            Phase::CfgConstruction => "CFG construction",
+            Phase::Checks => "checks",
+            Phase::UnusedLocals => "unused locals",
+            Phase::Optimization => "optimization",
+        }
+    }
+}
+
+/// Total duration of each phase, a phase which is run per function is summed up. The
+/// optimization is also broken down by passes in the order they are first run.
+#[derive(Default)]
+struct Timer {
+    durations: [Duration; Phase::ALL.len()],
+    passes: Vec<(&'static str, Duration)>,
+}
+
+impl Timer {
+    fn time<T>(&mut self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.durations[phase as usize] += start.elapsed();
+        result
+    }
+
+    fn add_passes(&mut self, durations: Vec<(&'static str, Duration)>) {
+        for (name, duration) in durations {
+            match self.passes.iter_mut().find(|(pass, _)| *pass == name) {
+                Some((_, total)) => *total += duration,
+                None => self.passes.push((name, duration)),
+            }
+        }
+    }
+
+    /// Prints the durations to stderr in the order of the phases followed by the passes
+    fn report(&self) {
+        let phases = Phase::ALL
+            .iter()
+            .map(|phase| (phase.name(), self.durations[*phase as usize]));
+        for (name, duration) in phases.chain(self.passes.iter().copied()) {
+            eprintln!(
+                "time: {:>10.3} ms  {}",
+                duration.as_secs_f64() * 1000.0,
+                name
+            );
+        }
+    }
+}
+
+/// Error which is already printed to stderr by run, main only exits with the error status
+#[derive(Debug)]
+struct Reported;
+
+impl fmt::Display for Reported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error is reported")
+    }
+}
+
+impl Error for Reported {}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        if !err.is::<Reported>() {
+            eprintln!("Error: {:?}", err);
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Options go before the source file name:
+/// --dump-ast          print the abstract syntax tree instead of IR
+/// --dump-ir           print IR, this is the default
+/// --emit-dot <file>   also write the control flow graph of each function in the DOT format
+/// -O0, -O1, -O2       level of the optimization, see OptLevel, -O0 is the default
+/// --time-passes       print the duration of each phase and optimization pass to stderr
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (file_name, options) = match args.split_last() {
+        Some(split) => split,
+        None => {
+            println!(
+                "Usage: ctl [--dump-ast | --dump-ir] [--emit-dot <file>] [--time-passes] \
+                 [-O0 | -O1 | -O2] <source file name>"
+            );
+            return Ok(());
+        }
+    };
+
+    let mut ast_only = false;
+    let mut dot_file = None;
+    let mut time_passes = false;
+    let mut level = OptLevel::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--dump-ast" => ast_only = true,
+            "--dump-ir" => ast_only = false,
+            "--time-passes" => time_passes = true,
+            "--emit-dot" => {
+                dot_file = Some(
+                    options
+                        .next()
+                        .ok_or("Option --emit-dot needs a file name")?,
+                )
+            }
+            _ => match OptLevel::from_option(option) {
+                Some(option_level) => level = option_level,
+                None => return Err(format!("Unknown option {}", option).into()),
+            },
+        }
     }
 
     // Work with file, get the file's contents to a String
-    let file = File::open(&args[1])?;
+    let file = File::open(file_name)?;
     let mut buf_reader = std::io::BufReader::new(file);
     let mut contents = String::new();
     buf_reader.read_to_string(&mut contents)?;
 
     // Parse the contents
-    let funcs = parse(contents)?;
+    let mut timer = Timer::default();
+    let parsed = timer
+        .time(Phase::Lexing, || lexing_with_lines(&contents))
+        .and_then(|(tokens, lines)| timer.time(Phase::Parsing, || parse_tokens(tokens, lines)));
+    let funcs = match parsed {
+        Ok(funcs) => funcs,
+        // An unexpected mark is reported with the snippet of the source only
+        Err(err @ ParseError::UnexpectedMark { line, col, .. }) => {
+            eprint!(
+                "{}",
+                format_error_at(&contents, line, col, &err.to_string())
+            );
+            return Err(Reported.into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if ast_only {
+        dump_ast(&funcs);
+        if time_passes {
+            timer.report();
+        }
+        return Ok(());
+    }
 
     // Generate IR for each function and dump it to the stdout
-    for func in funcs {
-        let mut ir = generate_instructions(&func);
-        build_intermediate_representation(&mut ir);
+    let mut module = timer.time(Phase::IrGeneration, || generate_module(&funcs))?;
+    let mut dot = String::new();
+    for ir in module.functions_mut() {
+        timer.time(Phase::CfgConstruction, || {
+            build_intermediate_representation(ir)
+        })?;
+        timer.time(Phase::Checks, || {
+            ir.verify()?;
+            ir.check_returns(ir.has_return_type())?;
+            ir.check_initialized()
+        })?;
+        if !ir.has_reachable_exit() {
+            eprintln!("warning: function {} never returns", ir.name());
+        }
+        for var in timer.time(Phase::UnusedLocals, || find_unused_locals(ir)) {
+            let name = ir.var_name(var).unwrap_or("<unnamed>");
+            match ir.source_line(var) {
+                Some(line) => eprintln!(
+                    "warning: line {}: variable {} in function {} is never read",
+                    line,
+                    name,
+                    ir.name()
+                ),
+                None => eprintln!(
+                    "warning: variable {} in function {} is never read",
+                    name,
+                    ir.name()
+                ),
+            }
+        }
+        let passes = timer.time(Phase::Optimization, || run_passes(ir, level))?;
+        timer.add_passes(passes);
 
-        print!("{}", ir.dump());
+        print!("{}", ir);
+        dot.push_str(&ir.to_dot());
+    }
+
+    if let Some(dot_file) = dot_file {
+        std::fs::write(dot_file, dot)?;
+    }
+    if time_passes {
+        timer.report();
     }
 
     Ok(())
@@ -0,0 +1,16 @@
+//! Canonical form of the instructions: operands of the commutative instructions are ordered by
+//! their ids, so equal computations written in a different order become equal instructions.
+
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::InstData;
+
+pub fn canonicalize(func: &mut Function) {
+    for data in func.insts_mut().iter_mut() {
+        match data {
+            InstData::Add(op1, op2) | InstData::Mul(op1, op2) if op1.0 > op2.0 => {
+                std::mem::swap(op1, op2);
+            }
+            _ => (),
+        }
+    }
+}
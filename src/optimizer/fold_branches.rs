@@ -0,0 +1,49 @@
+//! Comparisons of two Constants are known at compile time. Cmp becomes a Constant and Branch
+//! becomes a Jump to the successor which is always taken. The other successor may become
+//! unreachable, it is left in the graph without the arc from the folded block.
+
+use crate::optimizer::ir::basic_block::BlockId;
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::{InstData, InstId, Value};
+
+pub fn fold_branches(func: &mut Function) {
+    for id in 0..func.len() {
+        if let InstData::Cmp(op1, op2, cc) = func[InstId(id)] {
+            if let Some((left, right)) = constants(func, op1, op2) {
+                func[InstId(id)] = InstData::Constant(cc.eval(left, right) as Value);
+            }
+        }
+    }
+
+    let mut changed = false;
+    for block in 0..func.blocks().len() {
+        let last = match func.blocks()[block].last() {
+            Some(last) => *last,
+            None => continue,
+        };
+        let taken = match func[last] {
+            InstData::Branch(op1, op2, cc) => match constants(func, op1, op2) {
+                // The first successor is taken if the condition holds
+                Some((left, right)) => usize::from(!cc.eval(left, right)),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let succ = func.succs_of(BlockId(block))[taken];
+        *func.blocks_mut()[block].succs_mut() = vec![succ];
+        func[last] = InstData::Jump;
+        changed = true;
+    }
+
+    if changed {
+        func.rebuild_predecessors();
+    }
+}
+
+fn constants(func: &Function, op1: InstId, op2: InstId) -> Option<(Value, Value)> {
+    match (&func[op1], &func[op2]) {
+        (InstData::Constant(left), InstData::Constant(right)) => Some((*left, *right)),
+        _ => None,
+    }
+}
@@ -0,0 +1,110 @@
+//! If-conversion: a diamond where each of the two branches only stores a value to the same
+//! variable becomes a Select of the value, so there is no branch anymore:
+//!
+//! ```text
+//! BB 0: Branch %0 == %2          BB 0: %13 = Cmp %0 == %2
+//! BB 1: Store %5 at %1      =>         %14 = Select %13, %5, %8
+//! BB 2: Store %8 at %1                  15 Store %14 at %1
+//!                                        4 Jump
+//! ```
+//!
+//! Pure instructions of the branches, e.g. the stored Constants, are moved before the Select.
+//! The blocks of the branches are left empty and unreachable.
+
+use crate::optimizer::ir::basic_block::BlockId;
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::{InstData, InstId};
+
+pub fn if_conversion(func: &mut Function) {
+    for block in 0..func.blocks().len() {
+        if let Some((then, alter)) = find_diamond(func, BlockId(block)) {
+            convert(func, BlockId(block), then, alter);
+        }
+    }
+}
+
+/// Returns the successors of the block if it ends with a Branch to two blocks which can be
+/// converted.
+fn find_diamond(func: &Function, block: BlockId) -> Option<(BlockId, BlockId)> {
+    let last = (*func.blocks()[block.0].last())?;
+    if !matches!(func[last], InstData::Branch(_, _, _)) {
+        return None;
+    }
+
+    let (then, alter) = match func.succs_of(block) {
+        [then, alter] if then != alter && *then != block && *alter != block => (*then, *alter),
+        _ => return None,
+    };
+
+    let join = func.succs_of(then);
+    if join.len() != 1 || join != func.succs_of(alter) || join[0] == then || join[0] == alter {
+        return None;
+    }
+
+    let slot = stored_variable(func, then, block)?;
+    if stored_variable(func, alter, block)? != slot {
+        return None;
+    }
+
+    Some((then, alter))
+}
+
+/// Returns the variable if the block is only reached from `pred`, ends with Jump and has exactly
+/// one Store besides the pure instructions.
+fn stored_variable(func: &Function, block: BlockId, pred: BlockId) -> Option<InstId> {
+    if func.preds_of(block) != [pred] {
+        return None;
+    }
+
+    let bb = &func.blocks()[block.0];
+    if !matches!(bb.last().map(|last| &func[last]), Some(InstData::Jump)) {
+        return None;
+    }
+
+    let mut slot = None;
+    for id in bb.insts(func.layout()) {
+        match func[id] {
+            InstData::Store(_, dest) if slot.is_none() => slot = Some(dest),
+            InstData::Jump => (),
+            ref data if data.is_pure() => (),
+            _ => return None,
+        }
+    }
+
+    slot
+}
+
+fn convert(func: &mut Function, block: BlockId, then: BlockId, alter: BlockId) {
+    let branch = func.blocks()[block.0].last().unwrap();
+    let (op1, op2, cc) = match func[branch] {
+        InstData::Branch(op1, op2, cc) => (op1, op2, cc),
+        _ => unreachable!(),
+    };
+    let join = func.succs_of(then)[0];
+
+    let mut values = Vec::new();
+    let mut slot = InstId::default();
+    for arm in [then, alter] {
+        let insts: Vec<InstId> = func.blocks()[arm.0].insts(func.layout()).collect();
+        for id in insts {
+            match func[id] {
+                InstData::Store(value, dest) => {
+                    values.push(value);
+                    slot = dest;
+                    func.unlink_inst(id);
+                }
+                InstData::Jump => func.unlink_inst(id),
+                _ => func.move_before(branch, id),
+            }
+        }
+        func.blocks_mut()[arm.0].succs_mut().clear();
+    }
+
+    let cond = func.insert_before(branch, InstData::Cmp(op1, op2, cc));
+    let select = func.insert_before(branch, InstData::Select(cond, values[0], values[1]));
+    func.insert_before(branch, InstData::Store(select, slot));
+    func[branch] = InstData::Jump;
+
+    *func.blocks_mut()[block.0].succs_mut() = vec![join];
+    func.rebuild_predecessors();
+}
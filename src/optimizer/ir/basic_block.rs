@@ -1,11 +1,14 @@
+use crate::optimizer::ir::function::{DumpOptions, Function};
 use crate::optimizer::ir::inst::{InstData, InstId};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BlockId(pub usize);
 
-#[derive(Clone)]
+/// Node of the doubly linked list of instructions inside a basic block.
+#[derive(Clone, Default, PartialEq)]
 pub struct InstNode {
     block: Option<BlockId>,
+    prev: Option<InstId>,
     next: Option<InstId>,
 }
 
@@ -13,6 +16,7 @@ impl InstNode {
     pub fn new() -> Self {
         Self {
             block: None,
+            prev: None,
             next: None,
         }
     }
@@ -25,6 +29,14 @@ impl InstNode {
         &mut self.block
     }
 
+    pub fn prev(&self) -> &Option<InstId> {
+        &self.prev
+    }
+
+    pub fn prev_mut(&mut self) -> &mut Option<InstId> {
+        &mut self.prev
+    }
+
     pub fn next(&self) -> &Option<InstId> {
         &self.next
     }
@@ -34,7 +46,7 @@ impl InstNode {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default, PartialEq)]
 pub struct BasicBlock {
     // Predecessors and successors
     preds: Vec<BlockId>,
@@ -55,10 +67,27 @@ impl BasicBlock {
         }
     }
 
+    /// A block without instructions, e.g. a just created one.
+    pub fn is_empty(&self) -> bool {
+        self.first.is_none()
+    }
+
+    pub fn preds(&self) -> &[BlockId] {
+        &self.preds
+    }
+
+    pub fn preds_mut(&mut self) -> &mut Vec<BlockId> {
+        &mut self.preds
+    }
+
     pub fn succs(&self) -> &[BlockId] {
         &self.succs
     }
 
+    pub fn succs_mut(&mut self) -> &mut Vec<BlockId> {
+        &mut self.succs
+    }
+
     pub fn first(&self) -> &Option<InstId> {
         &self.first
     }
@@ -83,6 +112,16 @@ impl BasicBlock {
         &mut self.last
     }
 
+    /// Iterates over the instructions of the block in the layout order.
+    pub fn insts<'a>(&'a self, layout: &'a [InstNode]) -> impl Iterator<Item = InstId> + 'a {
+        std::iter::successors(self.first, move |id| layout[id.0].next)
+    }
+
+    /// Returns the last instruction of the block if it is a terminator.
+    pub fn terminator(&self, func: &Function) -> Option<InstId> {
+        self.last.filter(|last| func[*last].is_terminator())
+    }
+
     fn dump_preds(&self) -> String {
         let mut result = String::new();
 
@@ -113,10 +152,10 @@ impl BasicBlock {
         result
     }
 
-    pub fn dump(&self, insts: &[InstData], layout: &[InstNode]) -> String {
+    pub fn dump(&self, insts: &[InstData], layout: &[InstNode], opts: DumpOptions) -> String {
         let mut result = String::new();
 
-        if let None = self.first {
+        if self.is_empty() {
             return result;
         }
 
@@ -125,12 +164,14 @@ impl BasicBlock {
             self.dump_preds(),
             self.dump_succs()
         ));
-        let mut to_inst = &self.first;
-        while let Some(id) = to_inst {
-            result.push_str(&insts[to_inst.unwrap().0].dump(*id));
+        for id in self.insts(layout) {
+            match insts[id.0] {
+                InstData::Constant(value) if opts.hex_constants => {
+                    result.push_str(&format!("%{} = Constant {:#x}", id, value))
+                }
+                ref data => result.push_str(&data.dump(id)),
+            }
             result.push('\n');
-
-            to_inst = &layout[to_inst.unwrap().0].next;
         }
 
         result
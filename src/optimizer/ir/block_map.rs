@@ -0,0 +1,63 @@
+use std::ops::{Index, IndexMut};
+
+use crate::optimizer::ir::basic_block::BlockId;
+
+/// Value for each basic block of a function, e.g. a state of an analysis. Indexed by BlockId.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMap<T>(Vec<T>);
+
+impl<T: Clone> BlockMap<T> {
+    /// Creates the map of `len` blocks with the same value.
+    pub fn new(len: usize, value: T) -> Self {
+        BlockMap(vec![value; len])
+    }
+}
+
+impl<T> BlockMap<T> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the values with their blocks.
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (BlockId, &T)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(id, value)| (BlockId(id), value))
+    }
+}
+
+impl<T> From<Vec<T>> for BlockMap<T> {
+    fn from(values: Vec<T>) -> Self {
+        BlockMap(values)
+    }
+}
+
+impl<T> Index<BlockId> for BlockMap<T> {
+    type Output = T;
+
+    fn index(&self, block: BlockId) -> &Self::Output {
+        match self.0.get(block.0) {
+            Some(value) => value,
+            None => panic!(
+                "Block {} is out of the map of {} blocks",
+                block.0,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<BlockId> for BlockMap<T> {
+    fn index_mut(&mut self, block: BlockId) -> &mut Self::Output {
+        let len = self.len();
+        match self.0.get_mut(block.0) {
+            Some(value) => value,
+            None => panic!("Block {} is out of the map of {} blocks", block.0, len),
+        }
+    }
+}
@@ -1,14 +1,25 @@
 use std::collections::HashMap;
 
 use crate::optimizer::ir::basic_block::{BasicBlock, BlockId, InstNode};
+use crate::optimizer::ir::block_map::BlockMap;
 use crate::optimizer::ir::inst::{InstData, InstId, Value};
 
+/// Function is cloned in order to compare it before and after a change.
+#[derive(Clone)]
 pub struct Function {
     name: String,
+    // Number of parameters, these are the leading Parameter instructions
+    params: usize,
+    // Does the function have a return type
+    return_type: bool,
     insts: Vec<InstData>,
     constants: HashMap<Value, InstId>,
     layout: Vec<InstNode>,
     blocks: Vec<BasicBlock>,
+    // Line of the source statement of each instruction, None for the ones created by the passes
+    source_lines: Vec<Option<usize>>,
+    // Name of the source variable of each Alloc
+    var_names: HashMap<InstId, String>,
 }
 
 impl Function {
@@ -16,18 +27,55 @@ impl Function {
         const AVERAGE_MINIMUM_COUNT: usize = 20;
 
         Function {
-            name: name,
+            name,
+            params: 0,
+            return_type: false,
             insts: Vec::<InstData>::with_capacity(AVERAGE_MINIMUM_COUNT),
             constants: HashMap::new(),
             layout: Vec::new(),
             blocks: Vec::new(),
+            source_lines: Vec::new(),
+            var_names: HashMap::new(),
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn params(&self) -> usize {
+        self.params
+    }
+
+    pub fn params_mut(&mut self) -> &mut usize {
+        &mut self.params
+    }
+
+    pub fn has_return_type(&self) -> bool {
+        self.return_type
+    }
+
+    pub fn return_type_mut(&mut self) -> &mut bool {
+        &mut self.return_type
+    }
+
     pub fn len(&self) -> usize {
         self.insts.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.insts.is_empty()
+    }
+
+    /// Number of instructions placed in the basic blocks. Unlike len() it doesn't count the
+    /// instructions which are not linked into any block, e.g. Invalid placeholders.
+    pub fn real_len(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| block.insts(&self.layout).count())
+            .sum()
+    }
+
     pub fn insts(&self) -> &Vec<InstData> {
         &self.insts
     }
@@ -36,6 +84,28 @@ impl Function {
         &mut self.insts
     }
 
+    /// Lines of the source statements indexed by InstId.
+    pub fn source_lines(&self) -> &Vec<Option<usize>> {
+        &self.source_lines
+    }
+
+    pub fn source_lines_mut(&mut self) -> &mut Vec<Option<usize>> {
+        &mut self.source_lines
+    }
+
+    pub fn source_line(&self, inst: InstId) -> Option<usize> {
+        self.source_lines[inst.0]
+    }
+
+    /// Name of the source variable which lives in the Alloc.
+    pub fn var_name(&self, alloc: InstId) -> Option<&str> {
+        self.var_names.get(&alloc).map(String::as_str)
+    }
+
+    pub fn var_names_mut(&mut self) -> &mut HashMap<InstId, String> {
+        &mut self.var_names
+    }
+
     pub fn constants(&self) -> &HashMap<Value, InstId> {
         &self.constants
     }
@@ -44,6 +114,19 @@ impl Function {
         &mut self.constants
     }
 
+    /// Returns the constants sorted by value. Iteration order of constants() is not stable, so
+    /// use this one when the constants are listed.
+    pub fn constants_sorted(&self) -> Vec<(Value, InstId)> {
+        let mut constants: Vec<(Value, InstId)> = self
+            .constants
+            .iter()
+            .map(|(value, id)| (*value, *id))
+            .collect();
+        constants.sort_unstable_by_key(|(value, _)| *value);
+
+        constants
+    }
+
     pub fn layout(&self) -> &Vec<InstNode> {
         &self.layout
     }
@@ -60,9 +143,138 @@ impl Function {
         &mut self.blocks
     }
 
+    /// Returns the successors of the block.
+    ///
+    /// ```
+    /// use ctl::frontend::inst_builder::generate_instructions;
+    /// use ctl::frontend::parser::parse;
+    /// use ctl::optimizer::ir::basic_block::BlockId;
+    /// use ctl::optimizer::ir_builder::build_intermediate_representation;
+    ///
+    /// let funcs = parse("fn main(p: i64) { if (p == 0) {} }").unwrap();
+    /// let mut func = generate_instructions(&funcs[0]).unwrap();
+    /// build_intermediate_representation(&mut func).unwrap();
+    ///
+    /// assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1), BlockId(1)]);
+    /// assert_eq!(func.preds_of(BlockId(1)), &[BlockId(0), BlockId(0)]);
+    /// ```
+    pub fn succs_of(&self, block: BlockId) -> &[BlockId] {
+        self.blocks[block.0].succs()
+    }
+
+    /// Returns the predecessors of the block.
+    pub fn preds_of(&self, block: BlockId) -> &[BlockId] {
+        self.blocks[block.0].preds()
+    }
+
+    /// Recomputes the predecessors of every block from the successors. A pass which changes the
+    /// control flow may only update the successors and call this at the end.
+    pub fn rebuild_predecessors(&mut self) {
+        for block in self.blocks.iter_mut() {
+            block.preds_mut().clear();
+        }
+
+        for id in 0..self.blocks.len() {
+            for i in 0..self.blocks[id].succs().len() {
+                let succ = self.blocks[id].succs()[i];
+                self.blocks[succ.0].add_pred(BlockId(id));
+            }
+        }
+    }
+
+    /// Entry of the control flow graph is always the first basic block.
+    pub fn entry_block(&self) -> BlockId {
+        debug_assert!(!self.blocks.is_empty());
+        BlockId(0)
+    }
+
+    /// Returns the basic blocks which end with Return or ReturnVoid.
+    pub fn exit_blocks(&self) -> Vec<BlockId> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| {
+                block.last().is_some_and(|last| {
+                    matches!(self[last], InstData::Return(_) | InstData::ReturnVoid)
+                })
+            })
+            .map(|(id, _)| BlockId(id))
+            .collect()
+    }
+
+    /// Returns false if no Return or ReturnVoid is reachable from the entry block, e.g. the
+    /// function ends with an infinite loop.
+    pub fn has_reachable_exit(&self) -> bool {
+        let reachable = self.reachable_blocks();
+        self.exit_blocks().iter().any(|exit| reachable[*exit])
+    }
+
+    /// Marks the blocks which are reachable from the entry block.
+    pub fn reachable_blocks(&self) -> BlockMap<bool> {
+        let mut visited = BlockMap::new(self.blocks.len(), false);
+        let mut stack = vec![self.entry_block()];
+        while let Some(block) = stack.pop() {
+            if visited[block] {
+                continue;
+            }
+            visited[block] = true;
+            stack.extend_from_slice(self.succs_of(block));
+        }
+
+        visited
+    }
+
+    /// Negates the condition of the Branch terminating the block and swaps its successors, so
+    /// the control flow stays the same.
+    pub fn invert_branch(&mut self, block: BlockId) {
+        let last = self.blocks[block.0].last().unwrap();
+        match &mut self[last] {
+            InstData::Branch(_, _, cc) => *cc = cc.negate(),
+            data => panic!("Block {} ends with {} instead of Branch", block.0, data),
+        }
+        self.blocks[block.0].succs_mut().swap(0, 1);
+    }
+
+    /// Walks the instructions of the blocks in the layout order and replaces each one for which
+    /// `f` returns new data. The layout stays intact, so `f` shouldn't turn an instruction into a
+    /// terminator or vice versa.
+    pub fn rewrite<F: FnMut(InstId, &InstData) -> Option<InstData>>(&mut self, mut f: F) {
+        for block in self.blocks.iter() {
+            for id in block.insts(&self.layout) {
+                if let Some(data) = f(id, &self.insts[id.0]) {
+                    self.insts[id.0] = data;
+                }
+            }
+        }
+    }
+
+    /// Returns the block of the instruction and its position in the block counted from 0. The
+    /// position is found by walking the layout back to the first instruction of the block.
+    pub fn instruction_index(&self, id: InstId) -> (BlockId, usize) {
+        let block = self.layout[id.0].block();
+        let index = std::iter::successors(*self.layout[id.0].prev(), |prev| {
+            *self.layout[prev.0].prev()
+        })
+        .count();
+        (block, index)
+    }
+
+    /// Returns true if `a` goes before `b`, both of them should be in one basic block.
+    pub fn comes_before(&self, a: InstId, b: InstId) -> bool {
+        let (block_a, index_a) = self.instruction_index(a);
+        let (block_b, index_b) = self.instruction_index(b);
+        assert_eq!(
+            block_a, block_b,
+            "Instructions {} and {} are in different blocks",
+            a, b
+        );
+        index_a < index_b
+    }
+
     pub fn create_inst(&mut self, data: InstData) -> InstId {
         self.insts.push(data);
         self.layout.push(InstNode::new());
+        self.source_lines.push(None);
         InstId(self.insts.len() - 1)
     }
 
@@ -72,6 +284,61 @@ impl Function {
         BlockId(len)
     }
 
+    /// Splits the arc from `pred` to `succ` with a new block which only jumps to `succ`. Returns
+    /// the new block. If there are two arcs between the blocks the first one is split. Panics if
+    /// there is no arc, the function is left unchanged then.
+    pub fn create_block_after(&mut self, pred: BlockId, succ: BlockId) -> BlockId {
+        let arc = self.blocks[pred.0].succs().iter().position(|s| *s == succ);
+        let back = self.blocks[succ.0].preds().iter().position(|p| *p == pred);
+        let (arc, back) = match (arc, back) {
+            (Some(arc), Some(back)) => (arc, back),
+            _ => panic!("No arc from BB {} to BB {}", pred.0, succ.0),
+        };
+
+        let block = self.create_block();
+        let jump = self.create_inst(InstData::Jump);
+        self.append_inst(jump, block);
+
+        self.blocks[pred.0].succs_mut()[arc] = block;
+        self.blocks[succ.0].preds_mut()[back] = block;
+        self.blocks[block.0].add_pred(pred);
+        self.blocks[block.0].add_succ(succ);
+
+        block
+    }
+
+    /// Removes the block and its arcs, the instructions of the block are unlinked but stay in the
+    /// function. BlockId is a position in the vector of blocks, so the blocks after the removed
+    /// one are renumbered instead of leaving a tombstone: every id greater than `block` is
+    /// decremented in the arcs and in the layout. Ids of these blocks kept by the caller become
+    /// stale. A predecessor loses its successor, so its terminator is the caller's business.
+    pub fn remove_block(&mut self, block: BlockId) {
+        assert!(block != self.entry_block(), "Entry block can't be removed");
+
+        let insts: Vec<InstId> = self.blocks[block.0].insts(&self.layout).collect();
+        for inst in insts {
+            self.layout[inst.0] = InstNode::new();
+        }
+        self.blocks.remove(block.0);
+
+        let renumber = |id: &mut BlockId| {
+            if id.0 > block.0 {
+                id.0 -= 1;
+            }
+        };
+        for bb in self.blocks.iter_mut() {
+            bb.preds_mut().retain(|pred| *pred != block);
+            bb.succs_mut().retain(|succ| *succ != block);
+            bb.preds_mut().iter_mut().for_each(renumber);
+            bb.succs_mut().iter_mut().for_each(renumber);
+        }
+        for node in self.layout.iter_mut() {
+            if let Some(id) = node.block_mut() {
+                renumber(id);
+            }
+        }
+    }
+
     pub fn append_inst(&mut self, inst: InstId, block: BlockId) {
         *self.layout[inst.0].block_mut() = Some(block);
         debug_assert!(
@@ -81,9 +348,9 @@ impl Function {
         );
         let bb = &mut self.blocks[block.0];
 
-        if let None = bb.first() {
+        if bb.first().is_none() {
             debug_assert!(
-                matches!(bb.last(), None),
+                bb.last().is_none(),
                 "BB: {}. First instruction is not set but last is",
                 block.0
             );
@@ -92,38 +359,428 @@ impl Function {
             return;
         }
 
-        if let None = bb.last() {
+        if bb.last().is_none() {
             debug_assert!(
-                matches!(bb.first(), None),
+                bb.first().is_none(),
                 "BB: {}. First instruction is not set but last is",
                 block.0
             );
         }
 
-        let last_node = &mut self.layout[bb.last().unwrap().0];
+        let last = bb.last().unwrap();
+        let last_node = &mut self.layout[last.0];
         debug_assert!(
-            matches!(last_node.next(), None),
+            last_node.next().is_none(),
             "Last instruction in BB {} has the next one",
             block.0
         );
         *last_node.next_mut() = Some(inst);
+        *self.layout[inst.0].prev_mut() = Some(last);
         *bb.last_mut() = Some(inst);
     }
+
+    /// Creates an instruction and places it right after `anchor` in the anchor's basic block.
+    pub fn insert_after(&mut self, anchor: InstId, data: InstData) -> InstId {
+        let block = self.layout[anchor.0].block();
+        let next = *self.layout[anchor.0].next();
+        let inst = self.create_inst(data);
+
+        let node = &mut self.layout[inst.0];
+        *node.block_mut() = Some(block);
+        *node.prev_mut() = Some(anchor);
+        *node.next_mut() = next;
+        *self.layout[anchor.0].next_mut() = Some(inst);
+
+        match next {
+            Some(next) => *self.layout[next.0].prev_mut() = Some(inst),
+            None => *self.blocks[block.0].last_mut() = Some(inst),
+        }
+
+        inst
+    }
+
+    /// Creates an instruction and places it right before `anchor` in the anchor's basic block.
+    pub fn insert_before(&mut self, anchor: InstId, data: InstData) -> InstId {
+        let inst = self.create_inst(data);
+        self.link_before(anchor, inst);
+        inst
+    }
+
+    /// Takes the instruction out of its basic block. The instruction stays in the function, so it
+    /// can be placed again.
+    pub fn unlink_inst(&mut self, inst: InstId) {
+        let block = self.layout[inst.0].block();
+        let prev = *self.layout[inst.0].prev();
+        let next = *self.layout[inst.0].next();
+
+        match prev {
+            Some(prev) => *self.layout[prev.0].next_mut() = next,
+            None => *self.blocks[block.0].first_mut() = next,
+        }
+        match next {
+            Some(next) => *self.layout[next.0].prev_mut() = prev,
+            None => *self.blocks[block.0].last_mut() = prev,
+        }
+        self.layout[inst.0] = InstNode::new();
+    }
+
+    /// Moves the instruction right before `anchor`, possibly to another basic block.
+    pub fn move_before(&mut self, anchor: InstId, inst: InstId) {
+        self.unlink_inst(inst);
+        self.link_before(anchor, inst);
+    }
+
+    fn link_before(&mut self, anchor: InstId, inst: InstId) {
+        let block = self.layout[anchor.0].block();
+        let prev = *self.layout[anchor.0].prev();
+
+        let node = &mut self.layout[inst.0];
+        *node.block_mut() = Some(block);
+        *node.prev_mut() = prev;
+        *node.next_mut() = Some(anchor);
+        *self.layout[anchor.0].prev_mut() = Some(inst);
+
+        match prev {
+            Some(prev) => *self.layout[prev.0].next_mut() = Some(inst),
+            None => *self.blocks[block.0].first_mut() = Some(inst),
+        }
+    }
+}
+
+impl Function {
+    /// Checks that a function with a return type can't reach its end without returning a value,
+    /// i.e. every path from the entry block meets a Return before the implicit ReturnVoid.
+    /// Must be called after building the control flow graph.
+    pub fn check_returns(&self, has_return_type: bool) -> Result<(), String> {
+        if !has_return_type {
+            return Ok(());
+        }
+
+        let mut visited = BlockMap::new(self.blocks.len(), false);
+        let mut stack = vec![self.entry_block()];
+        while let Some(block) = stack.pop() {
+            if visited[block] {
+                continue;
+            }
+            visited[block] = true;
+
+            let bb = &self.blocks[block.0];
+            let exit = bb
+                .insts(&self.layout)
+                .find(|id| matches!(self[*id], InstData::Return(_) | InstData::ReturnVoid));
+
+            match exit.map(|id| &self[id]) {
+                Some(InstData::Return(_)) => (),
+                Some(_) => {
+                    return Err(format!(
+                        "Function {} may reach its end without returning a value",
+                        self.name
+                    ))
+                }
+                None => stack.extend_from_slice(self.succs_of(block)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the invariants of the control flow graph:
+    /// * operands are the instructions which produce a value,
+    /// * Store writes to and Load reads from an Alloc,
+    /// * a terminator is only the last instruction of a block and the number of the block's
+    ///   successors matches it,
+    /// * successors and predecessors are symmetric.
+    pub fn verify(&self) -> Result<(), String> {
+        let error = |message: String| Err(format!("Function {}: {}", self.name, message));
+
+        for (block, bb) in self.blocks.iter().enumerate() {
+            for id in bb.insts(&self.layout) {
+                let data = &self[id];
+                for op in data.operands() {
+                    if op.0 >= self.insts.len() || !self[op].has_value() {
+                        return error(format!(
+                            "operand %{} of instruction {} has no value",
+                            op, id
+                        ));
+                    }
+                }
+
+                match data {
+                    InstData::Store(_, ptr) | InstData::Load(ptr)
+                        if !matches!(self[*ptr], InstData::Alloc) =>
+                    {
+                        return error(format!(
+                            "instruction {} accesses %{} which is not an Alloc",
+                            id, ptr
+                        ));
+                    }
+                    _ => (),
+                }
+
+                if data.is_terminator() && Some(id) != *bb.last() {
+                    return error(format!("terminator {} is not last in BB {}", id, block));
+                }
+            }
+
+            let succs = bb.succs().len();
+            let expected = match bb.last().map(|last| &self[last]) {
+                Some(InstData::Branch(_, _, _)) => Some(2),
+                Some(InstData::Jump) => Some(1),
+                Some(InstData::Return(_) | InstData::ReturnVoid | InstData::Trap) => Some(0),
+                _ => None,
+            };
+            if expected.is_some_and(|expected| expected != succs) {
+                return error(format!("BB {} has {} successors", block, succs));
+            }
+
+            for succ in bb.succs() {
+                let arcs = bb.succs().iter().filter(|s| *s == succ).count();
+                let back = self.preds_of(*succ).iter().filter(|p| p.0 == block).count();
+                if arcs != back {
+                    return error(format!(
+                        "BB {} is a successor of BB {} but not its predecessor",
+                        succ.0, block
+                    ));
+                }
+            }
+            for pred in bb.preds() {
+                if !self.succs_of(*pred).contains(&BlockId(block)) {
+                    return error(format!(
+                        "BB {} is a predecessor of BB {} but not its successor",
+                        pred.0, block
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every Load of a local variable reads a value stored to it before on all of the
+    /// paths from the entry block. Must be called after building the control flow graph.
+    /// Unreachable blocks are not checked.
+    pub fn check_initialized(&self) -> Result<(), String> {
+        let (block_in, _) = self.initialized_variables();
+
+        for (block, initialized) in block_in.iter_enumerated() {
+            let mut initialized = initialized.clone();
+            for id in self.blocks[block.0].insts(&self.layout) {
+                match self[id] {
+                    InstData::Store(_, dest) => initialized[dest.0] = true,
+                    InstData::Load(ptr) if !initialized[ptr.0] => {
+                        return Err(format!(
+                        "Variable %{} is possibly uninitialized at instruction {} in function {}",
+                        ptr.0, id.0, self.name
+                    ))
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forward data flow: a variable is initialized at the begining of a block if it is
+    /// initialized at the end of every predecessor. Returns the initialized variables (indexed by
+    /// the id of Alloc) at the begining and at the end of each block. Unreachable blocks have
+    /// every variable initialized.
+    fn initialized_variables(&self) -> (BlockMap<Vec<bool>>, BlockMap<Vec<bool>>) {
+        let stored_in = |block: BlockId, initialized: &mut Vec<bool>| {
+            for id in self.blocks[block.0].insts(&self.layout) {
+                if let InstData::Store(_, dest) = self[id] {
+                    initialized[dest.0] = true;
+                }
+            }
+        };
+
+        let entry = self.entry_block();
+        let mut block_in = BlockMap::new(self.blocks.len(), vec![true; self.insts.len()]);
+        let mut block_out = block_in.clone();
+        block_in[entry] = vec![false; self.insts.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in (0..self.blocks.len()).map(BlockId) {
+                let mut initialized = if block == entry {
+                    block_in[entry].clone()
+                } else {
+                    let mut meet = vec![true; self.insts.len()];
+                    for pred in self.preds_of(block) {
+                        for (var, init) in meet.iter_mut().enumerate() {
+                            *init &= block_out[*pred][var];
+                        }
+                    }
+                    block_in[block] = meet.clone();
+                    meet
+                };
+
+                stored_in(block, &mut initialized);
+                if initialized != block_out[block] {
+                    block_out[block] = initialized;
+                    changed = true;
+                }
+            }
+        }
+
+        (block_in, block_out)
+    }
+
+    /// Makes the value of a possibly uninitialized variable explicit: where a variable is
+    /// initialized on some of the paths merging in a block but not on the others, Undef is stored
+    /// to it at the end of each predecessor on the uninitialized paths.
+    pub fn store_undef_on_uninitialized_path(&mut self) {
+        loop {
+            let (_, block_out) = self.initialized_variables();
+
+            let mut stores = Vec::new();
+            for block in (0..self.blocks.len()).map(BlockId) {
+                let preds = self.preds_of(block);
+                for var in (0..self.insts.len()).filter(|var| self.insts[*var] == InstData::Alloc) {
+                    if preds.iter().any(|pred| block_out[*pred][var]) {
+                        for pred in preds.iter().filter(|pred| !block_out[**pred][var]) {
+                            // A block may be a predecessor several times, e.g. of both of the
+                            // successors of its Branch
+                            if !stores.contains(&(*pred, InstId(var))) {
+                                stores.push((*pred, InstId(var)));
+                            }
+                        }
+                    }
+                }
+            }
+
+            // The Stores go before the terminator, a block without one, e.g. an empty block left
+            // by if_conversion, is skipped
+            stores.retain(|(block, _)| self.blocks[block.0].terminator(self).is_some());
+            if stores.is_empty() {
+                return;
+            }
+
+            for (block, var) in stores {
+                let last = self.blocks[block.0].terminator(self).unwrap();
+                let undef = self.insert_before(last, InstData::Undef);
+                self.insert_before(last, InstData::Store(undef, var));
+            }
+        }
+    }
+}
+
+/// Options of the dump.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DumpOptions {
+    /// Values of the Constants are written in hexadecimal, e.g. `Constant 0xff`
+    pub hex_constants: bool,
 }
 
 impl Function {
     pub fn dump(&self) -> String {
+        self.dump_with(DumpOptions::default())
+    }
+
+    pub fn dump_with(&self, opts: DumpOptions) -> String {
         let mut result = String::new();
         result.push_str(&format!("Function {}:\n\n", self.name));
 
         for (id, block) in self.blocks.iter().enumerate() {
+            if block.is_empty() {
+                result.push_str(&format!("BB {}: <empty>\n\n", id));
+                continue;
+            }
+
             result.push_str(&format!("BB {}: ", id));
-            result.push_str(&block.dump(&self.insts, &self.layout));
+            result.push_str(&block.dump(&self.insts, &self.layout, opts));
             result.push('\n');
         }
 
         result
     }
+
+    /// Returns the control flow graph in the DOT format of Graphviz. Arcs from a block ending with
+    /// a Branch are labeled with the condition value leading to the successor.
+    pub fn to_dot(&self) -> String {
+        let mut result = format!("digraph \"{}\" {{\n", escape(&self.name));
+        result.push_str("    node [shape=box];\n");
+
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("BB {}\\l", id);
+            for inst in block.insts(&self.layout) {
+                label.push_str(&escape(&self[inst].dump(inst)));
+                label.push_str("\\l");
+            }
+            result.push_str(&format!("    bb{} [label=\"{}\"];\n", id, label));
+        }
+
+        for (id, block) in self.blocks.iter().enumerate() {
+            let succs = block.succs();
+            for (i, succ) in succs.iter().enumerate() {
+                result.push_str(&format!("    bb{} -> bb{}", id, succ.0));
+                if succs.len() == 2 {
+                    result.push_str(if i == 0 {
+                        " [label=true]"
+                    } else {
+                        " [label=false]"
+                    });
+                }
+                result.push_str(";\n");
+            }
+        }
+
+        result.push_str("}\n");
+        result
+    }
+}
+
+/// Escapes a string to be placed into double quotes in the DOT or JSON output: backslashes,
+/// double quotes and control characters.
+pub fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Functions are equal if they have the same name and the same basic blocks: predecessors,
+/// successors and the instructions in them. The ids of the instructions must match too. The
+/// instructions which are not placed in any block are not compared.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        if self.name != other.name || self.blocks.len() != other.blocks.len() {
+            return false;
+        }
+
+        self.blocks.iter().zip(other.blocks.iter()).all(|(b1, b2)| {
+            b1.preds() == b2.preds()
+                && b1.succs() == b2.succs()
+                && b1
+                    .insts(&self.layout)
+                    .map(|id| (id, &self[id]))
+                    .eq(b2.insts(&other.layout).map(|id| (id, &other[id])))
+        })
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.dump())
+    }
+}
+
+/// Debug output is the dump, so assert_eq! on functions shows the difference.
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.dump())
+    }
 }
 
 impl std::ops::Index<InstId> for Function {
@@ -1,5 +1,7 @@
+use crate::optimizer::ir::module::FuncId;
+
 /// Condition code
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Cc {
     Eq,
     Ne,
@@ -11,6 +13,34 @@ pub enum Cc {
     Invalid,
 }
 
+impl Cc {
+    /// Returns the condition code which holds exactly when this one does not.
+    pub fn negate(self) -> Cc {
+        match self {
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::Lt => Self::Ge,
+            Self::Ge => Self::Lt,
+            Self::Gt => Self::Le,
+            Self::Le => Self::Gt,
+            Self::Invalid => std::unreachable!(),
+        }
+    }
+
+    /// Compares two values with the condition code.
+    pub fn eval(self, left: Value, right: Value) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+            Self::Lt => left < right,
+            Self::Gt => left > right,
+            Self::Le => left <= right,
+            Self::Ge => left >= right,
+            Self::Invalid => std::unreachable!(),
+        }
+    }
+}
+
 use std::fmt;
 
 impl fmt::Display for Cc {
@@ -27,11 +57,20 @@ impl fmt::Display for Cc {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 pub struct InstId(pub usize);
 
+impl InstId {
+    /// Target of IfFalse or Goto which is not known yet while generating the linear IR. Every
+    /// target must be resolved before the instruction is dumped or the graph is built.
+    pub const UNRESOLVED: InstId = InstId(usize::MAX);
+}
+
 impl fmt::Display for InstId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == InstId::UNRESOLVED {
+            return write!(f, "?");
+        }
         write!(f, "{}", self.0)
     }
 }
@@ -42,7 +81,7 @@ type Dest = InstId;
 type Target = InstId;
 pub type Value = i64;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub enum InstData {
     Constant(Value),
     Parameter,
@@ -51,7 +90,9 @@ pub enum InstData {
     Store(InstId, Dest),
     Load(InstId),
 
-    // Binary instructions
+    // Binary instructions. Div and Mod have the semantics of Rust's `/` and `%` for i64: the
+    // quotient is truncated toward zero and the remainder has the sign of the dividend, e.g.
+    // -7 / 3 == -2 and -7 % 3 == -1.
     Add(Operand, Operand),
     Sub(Operand, Operand),
     Mul(Operand, Operand),
@@ -62,10 +103,35 @@ pub enum InstData {
 
     Neg(Operand),
 
+    // Conversions between the integer widths. A value of i32 is kept in the range of i32, Trunc
+    // wraps a value to this range and SignExtend widens a value of i32 to i64 keeping it as is.
+    Trunc(Operand),
+    SignExtend(Operand),
+
+    // Comparison which produces 1 if the condition is true and 0 otherwise
+    Cmp(Operand, Operand, Cc),
+    // Condition (a Cmp result) and two values, produces the first value if the condition is not
+    // equal to zero and the second one otherwise
+    Select(Operand, Operand, Operand),
+    // Value of a variable read before it is initialized on some path
+    Undef,
+
     Return(InstId),
     ReturnVoid,
 
-    Call(String, Vec<InstId>),
+    // Aborts the execution, e.g. when the condition of the built-in assert doesn't hold
+    Trap,
+
+    // Name of the callee, its index in the Module (None for built-in functions) and arguments
+    Call(String, Option<FuncId>, Vec<InstId>),
+
+    // Index of a function in the Module as a value, the name is resolved like the one of Call
+    FuncRef(String, Option<FuncId>),
+    // Call of the function which index is the value of the first operand, and arguments
+    CallIndirect(Operand, Vec<InstId>),
+
+    // Intrinsic of the built-in print with one argument
+    PrintInt(Operand),
 
     // Control flow instructions used during translation from AST to linear IR
     // (inst_builder module). `Target`s are instructions to which control is
@@ -75,7 +141,8 @@ pub enum InstData {
 
     // Control flow instructions which are built during translation from linear
     // IR to control flow graph with instructions in the basic blocks. Targets
-    // are placed as the successors of each BasicBlock.
+    // are placed as the successors of each BasicBlock. Branch goes to the
+    // first successor if the condition holds and to the second one otherwise.
     Branch(Operand, Operand, Cc),
     Jump,
 
@@ -99,6 +166,141 @@ impl InstData {
             _ => std::unreachable!(),
         }
     }
+
+    /// Returns the name of the opcode as it is written in the dump, without the operands.
+    pub fn opcode_name(&self) -> &'static str {
+        match self {
+            Self::Constant(_) => "Constant",
+            Self::Parameter => "Parameter",
+            Self::Alloc => "Alloc",
+            Self::Store(_, _) => "Store",
+            Self::Load(_) => "Load",
+            Self::Add(_, _) => "Add",
+            Self::Sub(_, _) => "Sub",
+            Self::Mul(_, _) => "Mul",
+            Self::Div(_, _) => "Div",
+            Self::Mod(_, _) => "Mod",
+            Self::Shl(_, _) => "Shl",
+            Self::Shr(_, _) => "Shr",
+            Self::Neg(_) => "Neg",
+            Self::Trunc(_) => "Trunc",
+            Self::SignExtend(_) => "SignExtend",
+            Self::Cmp(_, _, _) => "Cmp",
+            Self::Select(_, _, _) => "Select",
+            Self::Undef => "Undef",
+            Self::Return(_) => "Return",
+            Self::ReturnVoid => "ReturnVoid",
+            Self::Trap => "Trap",
+            Self::Call(_, _, _) => "Call",
+            Self::FuncRef(_, _) => "FuncRef",
+            Self::CallIndirect(_, _) => "CallIndirect",
+            Self::PrintInt(_) => "PrintInt",
+            Self::IfFalse(_, _, _, _) => "IfFalse",
+            Self::Goto(_) => "Goto",
+            Self::Branch(_, _, _) => "Branch",
+            Self::Jump => "Jump",
+            Self::Invalid => "Invalid",
+        }
+    }
+
+    /// Returns the values which the instruction takes as its inputs.
+    pub fn operands(&self) -> Vec<InstId> {
+        match self {
+            Self::Store(op1, op2)
+            | Self::Add(op1, op2)
+            | Self::Sub(op1, op2)
+            | Self::Mul(op1, op2)
+            | Self::Div(op1, op2)
+            | Self::Mod(op1, op2)
+            | Self::Shl(op1, op2)
+            | Self::Shr(op1, op2)
+            | Self::Cmp(op1, op2, _)
+            | Self::IfFalse(op1, op2, _, _)
+            | Self::Branch(op1, op2, _) => vec![*op1, *op2],
+
+            Self::Select(cond, op1, op2) => vec![*cond, *op1, *op2],
+
+            Self::Load(op)
+            | Self::Neg(op)
+            | Self::Trunc(op)
+            | Self::SignExtend(op)
+            | Self::Return(op)
+            | Self::PrintInt(op) => vec![*op],
+
+            Self::Call(_, _, args) => args.clone(),
+            Self::CallIndirect(callee, args) => {
+                let mut operands = vec![*callee];
+                operands.extend_from_slice(args);
+                operands
+            }
+
+            Self::Constant(_)
+            | Self::Parameter
+            | Self::Alloc
+            | Self::ReturnVoid
+            | Self::Trap
+            | Self::Undef
+            | Self::FuncRef(_, _)
+            | Self::Goto(_)
+            | Self::Jump
+            | Self::Invalid => Vec::new(),
+        }
+    }
+
+    /// Returns true for the instructions without side effects which can be removed when their
+    /// values are not used, or merged with an equal instruction. Load is not pure for now because
+    /// it depends on the Stores before it. Div and Mod are not pure because they trap on division
+    /// by zero.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Self::Constant(_)
+            | Self::Parameter
+            | Self::Alloc
+            | Self::Add(_, _)
+            | Self::Sub(_, _)
+            | Self::Mul(_, _)
+            | Self::Shl(_, _)
+            | Self::Shr(_, _)
+            | Self::Neg(_)
+            | Self::Trunc(_)
+            | Self::SignExtend(_)
+            | Self::Cmp(_, _, _)
+            | Self::Select(_, _, _)
+            | Self::Undef
+            | Self::FuncRef(_, _) => true,
+
+            Self::Load(_)
+            | Self::Store(_, _)
+            | Self::Div(_, _)
+            | Self::Mod(_, _)
+            | Self::Call(_, _, _)
+            | Self::CallIndirect(_, _)
+            | Self::PrintInt(_)
+            | Self::Return(_)
+            | Self::ReturnVoid
+            | Self::Trap
+            | Self::IfFalse(_, _, _, _)
+            | Self::Goto(_)
+            | Self::Branch(_, _, _)
+            | Self::Jump
+            | Self::Invalid => false,
+        }
+    }
+
+    /// Returns true for the instructions which end a basic block: control flow instructions
+    /// of both linear IR and control flow graph, the returns and Trap.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            Self::IfFalse(_, _, _, _)
+                | Self::Goto(_)
+                | Self::Branch(_, _, _)
+                | Self::Jump
+                | Self::Return(_)
+                | Self::ReturnVoid
+                | Self::Trap
+        )
+    }
 }
 
 impl fmt::Display for InstData {
@@ -119,24 +321,28 @@ impl fmt::Display for InstData {
             InstData::Shr(op1, op2) => write!(f, "Shr %{}, %{}", op1, op2),
 
             InstData::Neg(op) => write!(f, "Neg %{}", op),
+            InstData::Trunc(op) => write!(f, "Trunc %{}", op),
+            InstData::SignExtend(op) => write!(f, "SignExtend %{}", op),
+            InstData::Cmp(op1, op2, cc) => write!(f, "Cmp %{} {} %{}", op1, cc, op2),
+            InstData::Select(cond, op1, op2) => write!(f, "Select %{}, %{}, %{}", cond, op1, op2),
+            InstData::Undef => write!(f, "Undef"),
 
             InstData::Return(value) => write!(f, "Return %{}", value),
             InstData::ReturnVoid => write!(f, "ReturnVoid"),
+            InstData::Trap => write!(f, "Trap"),
 
-            InstData::Call(name, args) => {
+            InstData::Call(name, _, args) => {
                 write!(f, "Call {}, args: ", name)?;
-
-                for (i, arg) in args.iter().enumerate() {
-                    if i != args.len() - 1 {
-                        write!(f, "%{}, ", arg)?;
-                    } else {
-                        write!(f, "%{}", arg)?;
-                    }
-                }
-
-                Ok(())
+                write_args(f, args)
+            }
+            InstData::FuncRef(name, _) => write!(f, "FuncRef {}", name),
+            InstData::CallIndirect(callee, args) => {
+                write!(f, "CallIndirect %{}, args: ", callee)?;
+                write_args(f, args)
             }
 
+            InstData::PrintInt(op) => write!(f, "PrintInt %{}", op),
+
             InstData::IfFalse(op1, op2, cc, target) => {
                 write!(f, "IfFalse %{} {} %{}, goto {}", op1, cc, op2, target)
             }
@@ -152,45 +358,47 @@ impl fmt::Display for InstData {
     }
 }
 
-impl InstData {
-    pub fn dump(&self, id: InstId) -> String {
-        match self {
-            // Instructions which don't produce a value
-            InstData::Store(_, _)
-            | InstData::Goto(_)
-            | InstData::IfFalse(_, _, _, _)
-            | InstData::Jump
-            | InstData::Branch(_, _, _)
-            | InstData::ReturnVoid
-            | InstData::Return(_) => format!(" {} {}", id, self),
-
-            _ => format!("%{} = {}", id, self),
+fn write_args(f: &mut fmt::Formatter, args: &[InstId]) -> fmt::Result {
+    for (i, arg) in args.iter().enumerate() {
+        if i != args.len() - 1 {
+            write!(f, "%{}, ", arg)?;
+        } else {
+            write!(f, "%{}", arg)?;
         }
     }
-}
-
-#[derive(Clone, Copy, PartialEq)]
-pub struct BlockId(pub usize);
 
-#[derive(Clone)]
-pub struct InstNode {
-    block: Option<BlockId>,
-    next: Option<InstId>,
+    Ok(())
 }
 
-impl InstNode {
-    pub fn new() -> Self {
-        Self {
-            block: None,
-            next: None,
-        }
-    }
-
-    pub fn block(&self) -> BlockId {
-        self.block.unwrap()
+impl InstData {
+    /// Returns false for the instructions which don't produce a value.
+    pub fn has_value(&self) -> bool {
+        !matches!(
+            self,
+            InstData::Store(_, _)
+                | InstData::Goto(_)
+                | InstData::IfFalse(_, _, _, _)
+                | InstData::Jump
+                | InstData::Branch(_, _, _)
+                | InstData::ReturnVoid
+                | InstData::Return(_)
+                | InstData::Trap
+                | InstData::PrintInt(_)
+                | InstData::Invalid
+        )
     }
 
-    pub fn next(&self) -> &Option<InstId> {
-        &self.next
+    /// Panics on an unresolved target, it is a bug of the IR generation left unnoticed.
+    pub fn dump(&self, id: InstId) -> String {
+        assert!(
+            self.target() != Some(InstId::UNRESOLVED),
+            "Instruction {} has an unresolved target",
+            id
+        );
+        if self.has_value() {
+            format!("%{} = {}", id, self)
+        } else {
+            format!(" {} {}", id, self)
+        }
     }
 }
@@ -1,3 +1,5 @@
 pub mod basic_block;
+pub mod block_map;
 pub mod function;
 pub mod inst;
+pub mod module;
@@ -0,0 +1,81 @@
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::InstData;
+
+/// Index of a function in the Module
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FuncId(pub usize);
+
+/// Container of all the functions of a program. Calls between these functions are resolved to
+/// FuncId's which makes possible cross-function optimizations.
+pub struct Module {
+    functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn new() -> Module {
+        Module {
+            functions: Vec::new(),
+        }
+    }
+
+    pub fn functions(&self) -> &Vec<Function> {
+        &self.functions
+    }
+
+    pub fn functions_mut(&mut self) -> &mut Vec<Function> {
+        &mut self.functions
+    }
+
+    pub fn add_function(&mut self, func: Function) -> FuncId {
+        self.functions.push(func);
+        FuncId(self.functions.len() - 1)
+    }
+
+    pub fn function_by_name(&self, name: &str) -> Option<FuncId> {
+        self.functions
+            .iter()
+            .position(|func| func.name() == name)
+            .map(FuncId)
+    }
+
+    /// Writes FuncId's of the callees to every Call instruction of the module and of the
+    /// referenced functions to every FuncRef. Calls of the built-in functions (which are not in
+    /// the module) stay unresolved.
+    pub fn resolve_calls(&mut self) {
+        let names: Vec<String> = self
+            .functions
+            .iter()
+            .map(|func| func.name().to_string())
+            .collect();
+
+        for func in self.functions.iter_mut() {
+            for data in func.insts_mut().iter_mut() {
+                if let InstData::Call(name, ref mut callee, _)
+                | InstData::FuncRef(name, ref mut callee) = data
+                {
+                    *callee = names.iter().position(|n| n == name).map(FuncId);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Module {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Index<FuncId> for Module {
+    type Output = Function;
+
+    fn index(&self, id: FuncId) -> &Self::Output {
+        &self.functions[id.0]
+    }
+}
+
+impl std::ops::IndexMut<FuncId> for Module {
+    fn index_mut(&mut self, id: FuncId) -> &mut Self::Output {
+        &mut self.functions[id.0]
+    }
+}
@@ -2,7 +2,7 @@ use crate::optimizer::ir::basic_block::BlockId;
 use crate::optimizer::ir::function::Function;
 use crate::optimizer::ir::inst::{InstData, InstId};
 
-fn find_leaders(insts: &[InstData]) -> Vec<usize> {
+fn find_leaders(insts: &[InstData]) -> Result<Vec<usize>, String> {
     let mut leaders = Vec::new();
 
     for (i, data) in insts.iter().enumerate() {
@@ -13,22 +13,64 @@ fn find_leaders(insts: &[InstData]) -> Vec<usize> {
 
         // Target instruction and instruction following branch are leaders
         if let Some(target) = data.target() {
+            if target == InstId::UNRESOLVED {
+                return Err(format!("Instruction {} has an unresolved target", i));
+            }
+            if target.0 >= insts.len() {
+                return Err(format!(
+                    "Instruction {} branches to {} which is out of {} instructions",
+                    i,
+                    target,
+                    insts.len()
+                ));
+            }
+
             leaders.push(target.0);
-            leaders.push(i + 1);
+            if i + 1 < insts.len() {
+                leaders.push(i + 1);
+            }
+        }
+
+        // Instruction following Trap or a return is a leader too, e.g. after assert(false) or
+        // the dead statements after return
+        if let InstData::Trap | InstData::Return(_) | InstData::ReturnVoid = data {
+            if i + 1 < insts.len() {
+                leaders.push(i + 1);
+            }
         }
     }
 
     leaders.sort_unstable();
     leaders.dedup();
 
-    leaders
+    Ok(leaders)
+}
+
+/// Translates calls of the built-in print with one argument to the PrintInt intrinsic.
+fn lower_print_calls(f: &mut Function) {
+    for data in f.insts_mut().iter_mut() {
+        if let InstData::Call(name, None, args) = data {
+            if name == "print" && args.len() == 1 {
+                *data = InstData::PrintInt(args[0]);
+            }
+        }
+    }
 }
 
-pub fn build_intermediate_representation(f: &mut Function) {
+/// Splits the linear IR into the basic blocks and links them into the control flow graph. IfFalse
+/// is translated to Branch, the successors of its block are [true successor, false successor]
+/// where the true successor is the next block and the false successor is the block starting with
+/// the target of IfFalse. So the condition of Branch is the same as of IfFalse and the fall
+/// through stays the true side. Goto is translated to Jump with the only successor.
+/// Fails if a branch of the linear IR targets an instruction out of the function or the last
+/// instruction falls through the end of the function.
+pub fn build_intermediate_representation(f: &mut Function) -> Result<(), String> {
     debug_assert!(!f.insts().is_empty());
     debug_assert!(f.blocks().is_empty());
 
-    let leaders = find_leaders(f.insts());
+    lower_print_calls(f);
+
+    let leaders = find_leaders(f.insts())?;
 
     // Create a basic block for each leader except the last one. Fill it with
     // the respective instructions before the next leader.
@@ -52,15 +94,39 @@ pub fn build_intermediate_representation(f: &mut Function) {
     }
 
     let mut current = 0;
-    while current < f.blocks().len() - 1 {
+    while current < f.blocks().len() {
         let last_inst = f.blocks()[current].last().unwrap();
+        let falls_through = match &f[last_inst] {
+            InstData::Goto(_) => false,
+            data => !data.is_terminator() || matches!(data, InstData::IfFalse(..)),
+        };
+        if falls_through && current + 1 == f.blocks().len() {
+            return Err(format!(
+                "Instruction {} falls through the end of the function",
+                last_inst
+            ));
+        }
+
         match &f[last_inst] {
             InstData::IfFalse(op1, op2, cc, target) => {
-                let (op1_clone, op2_clone, cc_clone) = (op1.clone(), op2.clone(), cc.clone());
+                let (op1_clone, op2_clone, cc_clone) = (*op1, *op2, *cc);
 
                 // Add arcs from the current basic block to the target ones.
-                // True successor goes first.
+                // True successor goes first. It is always the next block as IfFalse falls
+                // through when the condition is true, whatever the direction of its target is.
                 let target_block = f.layout()[target.0].block();
+                debug_assert_eq!(
+                    f.layout()[last_inst.0 + 1].block(),
+                    BlockId(current + 1),
+                    "Instruction after IfFalse {} should start the next block",
+                    last_inst
+                );
+                debug_assert_eq!(
+                    f.blocks()[target_block.0].first(),
+                    &Some(*target),
+                    "Target of IfFalse {} should start a block",
+                    last_inst
+                );
 
                 // Arc to the true successor
                 f.blocks_mut()[current].add_succ(BlockId(current + 1));
@@ -85,19 +151,18 @@ pub fn build_intermediate_representation(f: &mut Function) {
             _ => (),
         };
 
-        // If the last instruction is not a Branch or a Jump then just add an
+        // If the block is not terminated (by a Branch, a Jump or a return) then just add an
         // arc from current to the next basic block.
-        match &f[last_inst] {
-            InstData::Branch(_, _, _) | InstData::Jump => (),
-            _ => {
-                f.blocks_mut()[current].add_succ(BlockId(current + 1));
-                f.blocks_mut()[current + 1].add_pred(BlockId(current));
+        if f.blocks()[current].terminator(f).is_none() {
+            f.blocks_mut()[current].add_succ(BlockId(current + 1));
+            f.blocks_mut()[current + 1].add_pred(BlockId(current));
 
-                let jump = f.create_inst(InstData::Jump);
-                f.append_inst(jump, BlockId(current));
-            }
-        };
+            let jump = f.create_inst(InstData::Jump);
+            f.append_inst(jump, BlockId(current));
+        }
 
         current += 1;
     }
+
+    Ok(())
 }
@@ -1,2 +1,9 @@
+pub mod canonicalize;
+pub mod fold_branches;
+pub mod if_conversion;
 pub mod ir;
 pub mod ir_builder;
+pub mod pipeline;
+pub mod remove_unreachable;
+pub mod specialize;
+pub mod unused_locals;
@@ -0,0 +1,82 @@
+//! Optimization passes run between building the control flow graph and dumping the IR. The
+//! function is verified after each pass, so a broken pass is caught right after it.
+
+use std::time::{Duration, Instant};
+
+use crate::optimizer::canonicalize::canonicalize;
+use crate::optimizer::fold_branches::fold_branches;
+use crate::optimizer::if_conversion::if_conversion;
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::remove_unreachable::remove_unreachable_blocks;
+
+/// Level of the optimization set by the -O option:
+/// O0 - no passes, this is the default
+/// O1 - folding of the constant branches and removal of the unreachable blocks
+/// O2 - O1 followed by canonicalization and if-conversion
+///
+/// There are no fold_constants, dead_code_elimination, simplify and gvn passes yet. Until they
+/// land, fold_branches and remove_unreachable_blocks stand for fold and DCE at O1, canonicalize
+/// and if_conversion for simplify and gvn at O2.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+}
+
+impl OptLevel {
+    /// Parses the command line option: -O0, -O1 or -O2.
+    pub fn from_option(option: &str) -> Option<OptLevel> {
+        match option {
+            "-O0" => Some(OptLevel::O0),
+            "-O1" => Some(OptLevel::O1),
+            "-O2" => Some(OptLevel::O2),
+            _ => None,
+        }
+    }
+}
+
+type Pass = (&'static str, fn(&mut Function));
+
+const O1_PASSES: [Pass; 2] = [
+    ("fold_branches", fold_branches),
+    ("remove_unreachable_blocks", remove_unreachable_blocks),
+];
+
+const O2_PASSES: [Pass; 3] = [
+    ("canonicalize", canonicalize),
+    ("if_conversion", if_conversion),
+    ("remove_unreachable_blocks", remove_unreachable_blocks),
+];
+
+fn passes(level: OptLevel) -> Vec<Pass> {
+    let mut passes = Vec::new();
+    if level != OptLevel::O0 {
+        passes.extend_from_slice(&O1_PASSES);
+    }
+    if level == OptLevel::O2 {
+        passes.extend_from_slice(&O2_PASSES);
+    }
+    passes
+}
+
+/// Runs the passes of the level on the function and returns the duration of each pass in the
+/// order they are run. Fails with the name of the pass after which the function doesn't pass
+/// `Function::verify`.
+pub fn run_passes(
+    func: &mut Function,
+    level: OptLevel,
+) -> Result<Vec<(&'static str, Duration)>, String> {
+    let mut durations = Vec::new();
+    for (name, pass) in passes(level) {
+        let start = Instant::now();
+        pass(func);
+        durations.push((name, start.elapsed()));
+
+        func.verify()
+            .map_err(|err| format!("{} after pass {}", err, name))?;
+    }
+
+    Ok(durations)
+}
@@ -0,0 +1,35 @@
+//! Removal of the basic blocks which can't be reached from the entry block, e.g. the ones left by
+//! fold_branches and if_conversion or the dead code after a return.
+
+use crate::optimizer::ir::basic_block::BlockId;
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::InstData;
+
+pub fn remove_unreachable_blocks(func: &mut Function) {
+    let reachable = func.reachable_blocks();
+
+    // Constants are shared by the whole function, so the one placed in an unreachable block may
+    // be used in a reachable one. It is moved to the entry block which dominates every block.
+    let mut used = vec![false; func.len()];
+    let mut constants = Vec::new();
+    for (block, bb) in func.blocks().iter().enumerate() {
+        for id in bb.insts(func.layout()) {
+            if reachable[BlockId(block)] {
+                func[id].operands().iter().for_each(|op| used[op.0] = true);
+            } else if let InstData::Constant(_) = func[id] {
+                constants.push(id);
+            }
+        }
+    }
+    let entry_last = func.blocks()[func.entry_block().0].last().unwrap();
+    for constant in constants.into_iter().filter(|constant| used[constant.0]) {
+        func.move_before(entry_last, constant);
+    }
+
+    // Blocks are removed from the last one, so the ids of the blocks left to remove stay valid
+    for block in (0..func.blocks().len()).rev() {
+        if !reachable[BlockId(block)] {
+            func.remove_block(BlockId(block));
+        }
+    }
+}
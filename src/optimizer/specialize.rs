@@ -0,0 +1,66 @@
+//! Interprocedural constant propagation of the arguments. A function which is called from exactly
+//! one place with constant arguments is specialized for them: its parameters become constants
+//! and the call passes no arguments.
+
+use crate::optimizer::ir::inst::{InstData, InstId};
+use crate::optimizer::ir::module::{FuncId, Module};
+
+pub fn specialize_single_caller(module: &mut Module) {
+    // Call sites of every function of the module: the caller and the Call instruction
+    let mut call_sites: Vec<Vec<(FuncId, InstId)>> = vec![Vec::new(); module.functions().len()];
+    // A referenced function may be called indirectly with any arguments
+    let mut referenced = vec![false; module.functions().len()];
+    for (caller, func) in module.functions().iter().enumerate() {
+        for (id, data) in func.insts().iter().enumerate() {
+            match data {
+                InstData::Call(_, Some(callee), _) => {
+                    call_sites[callee.0].push((FuncId(caller), InstId(id)))
+                }
+                InstData::FuncRef(_, Some(func)) => referenced[func.0] = true,
+                _ => (),
+            }
+        }
+    }
+
+    for (callee, sites) in call_sites.iter().enumerate() {
+        if referenced[callee] {
+            continue;
+        }
+
+        let (caller, call) = match sites[..] {
+            // A recursive function is called with other arguments from itself
+            [(caller, call)] if caller.0 != callee => (caller, call),
+            _ => continue,
+        };
+
+        let args = match &module[caller][call] {
+            InstData::Call(_, _, args) => args.clone(),
+            _ => std::unreachable!(),
+        };
+        let values: Option<Vec<i64>> = args
+            .iter()
+            .map(|arg| match module[caller][*arg] {
+                InstData::Constant(value) => Some(value),
+                _ => None,
+            })
+            .collect();
+        let values = match values {
+            Some(values) if !values.is_empty() => values,
+            _ => continue,
+        };
+
+        // Parameters are the leading instructions of the callee
+        let func = &mut module[FuncId(callee)];
+        debug_assert_eq!(func.params(), values.len());
+        for (param, value) in values.into_iter().enumerate() {
+            debug_assert!(matches!(func[InstId(param)], InstData::Parameter));
+            func[InstId(param)] = InstData::Constant(value);
+            func.constants_mut().entry(value).or_insert(InstId(param));
+        }
+        *func.params_mut() = 0;
+
+        if let InstData::Call(_, _, ref mut args) = module[caller][call] {
+            args.clear();
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Search of the local variables which are written but never read.
+
+use crate::optimizer::ir::function::Function;
+use crate::optimizer::ir::inst::{InstData, InstId};
+
+/// Returns the Allocs which are only stored to and never loaded from.
+pub fn find_unused_locals(func: &Function) -> Vec<InstId> {
+    let mut read = vec![false; func.len()];
+    for data in func.insts() {
+        if let InstData::Load(ptr) = data {
+            read[ptr.0] = true;
+        }
+    }
+
+    func.insts()
+        .iter()
+        .enumerate()
+        .filter(|(id, data)| matches!(data, InstData::Alloc) && !read[*id])
+        .map(|(id, _)| InstId(id))
+        .collect()
+}
@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Writes the source to a file in the temporary directory and returns its path.
+fn write_source(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ctl_{}_{}.rs", name, std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+/// Runs the compiler with the arguments and returns its standard output.
+fn run_ctl(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ctl"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "ctl {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn dump_ast_option() {
+    let path = write_source("dump_ast", "fn main() { print(1); }");
+    let path = path.to_str().unwrap();
+
+    let ast = run_ctl(&["--dump-ast", path]);
+    assert_eq!(
+        ast,
+        "--------Dump AST--------\nFunction main\n\tCall print, args: Int<1> , \n"
+    );
+
+    // IR is dumped by default and with --dump-ir
+    let ir = run_ctl(&[path]);
+    assert!(ir.starts_with("Function main:"));
+    assert_eq!(run_ctl(&["--dump-ir", path]), ir);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn emit_dot_option() {
+    let path = write_source("emit_dot", "fn foo() {} fn main() { foo(); }");
+    let dot_path = std::env::temp_dir().join(format!("ctl_emit_dot_{}.dot", std::process::id()));
+
+    let ir = run_ctl(&[
+        "--emit-dot",
+        dot_path.to_str().unwrap(),
+        path.to_str().unwrap(),
+    ]);
+    assert!(ir.starts_with("Function foo:"));
+
+    // One graph per function
+    let dot = std::fs::read_to_string(&dot_path).unwrap();
+    assert!(dot.starts_with("digraph \"foo\" {"));
+    assert_eq!(dot.matches("digraph").count(), 2);
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(dot_path).unwrap();
+}
+
+#[test]
+fn warn_unused_variable() {
+    let path = write_source("unused", "fn main() { let mut x: i64 = 0; }");
+    let output = Command::new(env!("CARGO_BIN_EXE_ctl"))
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: line 1: variable x in function main is never read\n"
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn time_passes_option() {
+    let path = write_source("time_passes", "fn main() { print(1); }");
+    let path = path.to_str().unwrap();
+
+    // One line per phase in the order they are run
+    let phases = |level: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ctl"))
+            .args(["--time-passes", level, path])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .starts_with("Function main:"));
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.lines().all(|line| line.starts_with("time: ")));
+        stderr
+            .lines()
+            .map(|line| line.split("ms  ").nth(1).unwrap().to_string())
+            .collect::<Vec<String>>()
+    };
+    let expected = [
+        "lexing",
+        "parsing",
+        "IR generation",
+        "CFG construction",
+        "checks",
+        "unused locals",
+        "optimization",
+    ];
+    assert_eq!(phases("-O0"), expected);
+
+    // Each pass is reported once after the phases even if it is run several times
+    let mut expected = expected.to_vec();
+    expected.extend([
+        "fold_branches",
+        "remove_unreachable_blocks",
+        "canonicalize",
+        "if_conversion",
+    ]);
+    assert_eq!(phases("-O2"), expected);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn dead_code_and_assigned_parameter() {
+    // Neither a statement after return nor an assignment to a parameter is an IR error
+    let path = write_source(
+        "dead_code",
+        "fn f(a: i64) { a = 5; print(a); }
+         fn main() { f(1); return; print(1); }",
+    );
+
+    let ir = run_ctl(&[path.to_str().unwrap()]);
+    assert!(ir.starts_with("Function f:"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn unexpected_mark_reported_once() {
+    let path = write_source("unexpected_mark", "fn main() {\n    a = 1 # 2;\n}");
+    let output = Command::new(env!("CARGO_BIN_EXE_ctl"))
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "line 2: unexpected mark '#'\n  |\n2 |     a = 1 # 2;\n  |           ^\n"
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn optimization_level_option() {
+    let path = write_source(
+        "opt_level",
+        "fn main() { if (1 as i64 > 2) { print(1); } print(2); }",
+    );
+    let path = path.to_str().unwrap();
+
+    // -O0 is the default
+    let o0 = run_ctl(&[path]);
+    assert_eq!(run_ctl(&["-O0", path]), o0);
+    assert!(o0.contains("Branch"));
+
+    // The branch is folded and the print under it is removed
+    let o1 = run_ctl(&["-O1", path]);
+    assert!(!o1.contains("Branch"));
+    assert_eq!(o1.matches("PrintInt").count(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
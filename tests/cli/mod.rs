@@ -0,0 +1 @@
+pub mod main_test;
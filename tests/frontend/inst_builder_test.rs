@@ -1,6 +1,7 @@
 use ctl::frontend::inst_builder::generate_instructions;
-use ctl::frontend::parser::parse;
+use ctl::frontend::parser::{parse, Node};
 use ctl::optimizer::ir::inst::{InstData, InstId};
+use ctl::optimizer::ir_builder::build_intermediate_representation;
 
 fn dump(insts: &[InstData]) -> String {
     let mut res = String::new();
@@ -24,7 +25,7 @@ fn generate_empty_function_no_param() {
     assert_eq!(funcs.len(), 1);
 
     // Try to generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -52,7 +53,7 @@ fn generate_empty_function_few_parameters() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -70,6 +71,49 @@ fn generate_empty_function_few_parameters() {
     assert_eq!(dump, expected);
 }
 
+#[test]
+fn generate_function_parameters_count() {
+    let source = "
+    fn foo(p0: i64, p1: i64) {}
+
+    fn main() {}
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 2);
+
+    // Generate IR instructions and check the number of parameters
+    let foo = generate_instructions(&funcs[0]).unwrap();
+    assert_eq!(foo.params(), 2);
+
+    let main = generate_instructions(&funcs[1]).unwrap();
+    assert_eq!(main.params(), 0);
+}
+
+#[test]
+fn generate_function_keeps_source_name() {
+    let source = "
+    fn foo() {}
+
+    fn main() {}
+    "
+    .to_string();
+
+    let funcs = parse(source).unwrap();
+
+    let mut foo = generate_instructions(&funcs[0]).unwrap();
+    let mut main = generate_instructions(&funcs[1]).unwrap();
+    assert_eq!(foo.name(), "foo");
+    assert_eq!(main.name(), "main");
+
+    build_intermediate_representation(&mut foo).unwrap();
+    build_intermediate_representation(&mut main).unwrap();
+    assert!(foo.dump().starts_with("Function foo:\n"));
+    assert!(main.dump().starts_with("Function main:\n"));
+}
+
 #[test]
 fn generate_function_returning_its_param() {
     let source = "
@@ -84,7 +128,7 @@ fn generate_function_returning_its_param() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -116,7 +160,7 @@ fn generate_function_returning_param_plus_local() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -152,7 +196,7 @@ fn generate_arithmetic_expression() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -177,7 +221,7 @@ fn generate_arithmetic_expression() {
 }
 
 #[test]
-fn generate_negate() {
+fn generate_negative_literal() {
     let source = "
     fn main() {
         let mut a: i64 = -1;
@@ -190,7 +234,40 @@ fn generate_negate() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let insts = func.insts();
+    assert!(!insts.is_empty());
+
+    // Dump these to a string
+    let dump = dump(insts);
+
+    // Create expected dump
+    let expected = "
+        %0 = Alloc
+        %1 = Constant -1
+         2 Store %1 at %0
+         3 ReturnVoid"
+        .to_string();
+
+    // Compare generated instructions with the expected ones
+    assert_eq!(dump, expected);
+}
+
+#[test]
+fn generate_negate() {
+    let source = "
+    fn main() {
+        let mut a: i64 = -(1);
+    }
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+
+    // Generate IR instructions
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -225,7 +302,7 @@ fn generate_shifts() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -266,7 +343,7 @@ fn generate_conditional_branch_with_assign() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -307,23 +384,22 @@ fn generate_conditional_branch_with_returns() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
     // Dump these to a string
     let dump = dump(insts);
 
-    // Create expected dump
+    // Create expected dump. Both paths return so there is neither a Goto over the false
+    // successor block nor an implicit return at the end.
     let expected = "
         %0 = Parameter
         %1 = Constant 0
-         2 IfFalse %0 == %1, goto 5
+         2 IfFalse %0 == %1, goto 4
          3 Return %1
-         4 Goto 7
-        %5 = Constant 1
-         6 Return %5
-         7 ReturnVoid"
+        %4 = Constant 1
+         5 Return %4"
         .to_string();
 
     // Compare generated instructions with the expected ones
@@ -349,7 +425,7 @@ fn generate_conditional_loop() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -401,7 +477,7 @@ fn generate_infinite_loop() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -424,11 +500,9 @@ fn generate_infinite_loop() {
         %4 = Constant 1
         %5 = Add %3, %4
          6 Store %5 at %0
-         7 Goto 3
-        ";
+         7 Goto 3";
 
-    // Return void
-    expected = expected.clone() + " 8 ReturnVoid";
+    // Control never falls through the loop so there is no implicit return
 
     // Compare generated instructions with the expected ones
     assert_eq!(dump, expected);
@@ -454,7 +528,7 @@ fn generate_conditional_loop_with_break() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -522,7 +596,7 @@ fn generate_conditional_loop_with_continues() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -613,7 +687,7 @@ fn generate_conditional_nested_loops_with_continues() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -719,7 +793,7 @@ fn generate_infinite_loop_with_break() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -780,7 +854,7 @@ fn generate_nested_infinite_loops() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -834,7 +908,7 @@ fn generate_call_no_arguments() {
     assert_eq!(funcs.len(), 1);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[0]);
+    let func = generate_instructions(&funcs[0]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -852,6 +926,33 @@ fn generate_call_no_arguments() {
     assert_eq!(dump, expected);
 }
 
+#[test]
+fn generate_print_several_arguments() {
+    let source = "
+    fn main() {
+        print(1, 2);
+    }
+    ";
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+
+    // Generate IR instructions, every argument is printed by a separate call
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let dump = dump(func.insts());
+
+    let expected = "
+        %0 = Constant 1
+        %1 = Call print, args: %0
+        %2 = Constant 2
+        %3 = Call print, args: %2
+         4 ReturnVoid"
+        .to_string();
+
+    assert_eq!(dump, expected);
+}
+
 #[test]
 fn generate_call_few_arguments() {
     let source = "
@@ -868,7 +969,7 @@ fn generate_call_few_arguments() {
     assert_eq!(funcs.len(), 2);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[1]);
+    let func = generate_instructions(&funcs[1]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -887,6 +988,50 @@ fn generate_call_few_arguments() {
     assert_eq!(dump, expected);
 }
 
+#[test]
+fn generate_call_nested_arithmetic_arguments() {
+    let source = "
+    fn abc(p1: i64, p2: i64) {}
+
+    fn main(p: i64) {
+        abc((p + 1) * (p - 2), -(p / 3 % 4));
+    }
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 2);
+
+    // Generate IR instructions
+    let func = generate_instructions(&funcs[1]).unwrap();
+    let insts = func.insts();
+    assert!(!insts.is_empty());
+
+    // Dump these to a string
+    let dump = dump(insts);
+
+    // Create expected dump
+    let expected = "
+        %0 = Parameter
+        %1 = Constant 1
+        %2 = Add %0, %1
+        %3 = Constant 2
+        %4 = Sub %0, %3
+        %5 = Mul %2, %4
+        %6 = Constant 3
+        %7 = Div %0, %6
+        %8 = Constant 4
+        %9 = Mod %7, %8
+        %10 = Neg %9
+        %11 = Call abc, args: %5, %10
+         12 ReturnVoid"
+        .to_string();
+
+    // Compare generated instructions with the expected ones
+    assert_eq!(dump, expected);
+}
+
 #[test]
 fn generate_call_as_expression() {
     let source = "
@@ -903,7 +1048,7 @@ fn generate_call_as_expression() {
     assert_eq!(funcs.len(), 2);
 
     // Generate IR instructions
-    let func = generate_instructions(&funcs[1]);
+    let func = generate_instructions(&funcs[1]).unwrap();
     let insts = func.insts();
     assert!(!insts.is_empty());
 
@@ -922,3 +1067,712 @@ fn generate_call_as_expression() {
     // Compare generated instructions with the expected ones
     assert_eq!(dump, expected);
 }
+
+#[test]
+fn generate_division_by_zero() {
+    let source = "
+    fn div(x: i64) -> i64 {
+        return x / 0;
+    }
+
+    fn rem(x: i64) -> i64 {
+        return x % 0;
+    }
+
+    fn half(x: i64) -> i64 {
+        return x / 2;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 3);
+
+    // Division and modulo by a literal zero are rejected
+    match generate_instructions(&funcs[0]) {
+        Err(message) => assert_eq!(message, "Division by zero at line 3 in function div"),
+        Ok(_) => panic!("Expected an error on division by zero"),
+    }
+    match generate_instructions(&funcs[1]) {
+        Err(message) => assert_eq!(message, "Modulo by zero at line 7 in function rem"),
+        Ok(_) => panic!("Expected an error on modulo by zero"),
+    }
+
+    // Division by a non-zero literal is fine
+    let func = generate_instructions(&funcs[2]).unwrap();
+    let expected = "
+        %0 = Parameter
+        %1 = Constant 2
+        %2 = Div %0, %1
+         3 Return %2"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_shift_out_of_range() {
+    let source = "
+    fn left(x: i64) -> i64 {
+        return x << 64;
+    }
+
+    fn right(x: i64) -> i64 {
+        return x >> -1;
+    }
+
+    fn shift(x: i64) -> i64 {
+        return x << 3;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 3);
+
+    // Shift amounts out of the bit width of i64 are rejected
+    match generate_instructions(&funcs[0]) {
+        Err(message) => assert_eq!(
+            message,
+            "Shift amount 64 is out of range 0..64 at line 3 in function left"
+        ),
+        Ok(_) => panic!("Expected an error on shift by 64"),
+    }
+    match generate_instructions(&funcs[1]) {
+        Err(message) => assert_eq!(
+            message,
+            "Shift amount -1 is out of range 0..64 at line 7 in function right"
+        ),
+        Ok(_) => panic!("Expected an error on shift by -1"),
+    }
+
+    let func = generate_instructions(&funcs[2]).unwrap();
+    let expected = "
+        %0 = Parameter
+        %1 = Constant 3
+        %2 = Shl %0, %1
+         3 Return %2"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_constant_conditions() {
+    let source = "
+    fn main() {
+        let mut a: i64 = 0;
+        if (1 == 2) {
+            a = 1;
+        } else {
+            a = 2;
+        }
+        if (true) {
+            a = 3;
+        }
+        while (3 < 2) {
+            a = 4;
+        }
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+
+    // Only the taken paths are generated, there are no branches
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let expected = "
+        %0 = Alloc
+        %1 = Constant 0
+         2 Store %1 at %0
+        %3 = Constant 2
+         4 Store %3 at %0
+        %5 = Constant 3
+         6 Store %5 at %0
+         7 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_call_in_comparison() {
+    let source = "
+    fn calc(p: i64) -> i64 {
+        return p;
+    }
+
+    fn main() {
+        if (calc(0) == 1) {}
+        while (calc(1) < 2) {}
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 2);
+
+    let mut func = generate_instructions(&funcs[1]).unwrap();
+    let expected = "
+        %0 = Constant 0
+        %1 = Call calc, args: %0
+        %2 = Constant 1
+         3 IfFalse %1 == %2, goto 4
+        %4 = Call calc, args: %2
+        %5 = Constant 2
+         6 IfFalse %4 < %5, goto 8
+         7 Goto 4
+         8 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+
+    // The result of the call is the operand of the branch
+    build_intermediate_representation(&mut func).unwrap();
+    assert!(matches!(
+        func[InstId(3)],
+        InstData::Branch(InstId(1), InstId(2), _)
+    ));
+}
+
+#[test]
+fn generate_arithmetic_in_comparison() {
+    let source = "
+    fn main(a: i64, b: i64) {
+        if (a + 1 == b - 2) {}
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+
+    // Both sides of the comparison are computed before the branch
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let expected = "
+        %0 = Parameter
+        %1 = Parameter
+        %2 = Constant 1
+        %3 = Add %0, %2
+        %4 = Constant 2
+        %5 = Sub %1, %4
+         6 IfFalse %3 == %5, goto 7
+         7 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_comparison_value() {
+    let source = "
+    fn main(a: i64, b: i64) {
+        let mut c: bool = a < b;
+        if (c) {
+            c = false;
+        }
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+
+    // The comparison result is stored to the boolean local, the local is used as a condition by
+    // comparing it with zero
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let expected = "
+        %0 = Parameter
+        %1 = Parameter
+        %2 = Alloc
+        %3 = Cmp %0 < %1
+         4 Store %3 at %2
+        %5 = Load %2
+        %6 = Constant 0
+         7 IfFalse %5 != %6, goto 9
+         8 Store %6 at %2
+         9 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_labeled_break_of_outer_loop() {
+    let source = "
+    fn main(p0: i64, p1: i64) {
+        'outer: while (p0 > 0) {
+            while (true) {
+                if (p1 == 2) {
+                    break 'outer;
+                }
+                continue 'outer;
+            }
+        }
+    }
+    "
+    .to_string();
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let dump = dump(func.insts());
+
+    // The labeled break targets the exit of the outer loop and the labeled continue its begining
+    let expected = "
+        %0 = Parameter
+        %1 = Parameter
+        %2 = Constant 0
+         3 IfFalse %0 > %2, goto 10
+        %4 = Constant 2
+         5 IfFalse %1 == %4, goto 7
+         6 Goto 10
+         7 Goto 3
+         8 Goto 4
+         9 Goto 3
+         10 ReturnVoid"
+        .to_string();
+
+    assert_eq!(dump, expected);
+}
+
+#[test]
+fn generate_let_without_initializer() {
+    let source = "
+    fn main(p: i64) -> i64 {
+        let mut a: i64;
+        a = p;
+        return a;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let mut func = generate_instructions(&funcs[0]).unwrap();
+
+    // There is no Store right after the Alloc
+    let expected = "
+        %0 = Parameter
+        %1 = Alloc
+         2 Store %0 at %1
+        %3 = Load %1
+         4 Return %3"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+
+    build_intermediate_representation(&mut func).unwrap();
+    assert_eq!(func.check_initialized(), Ok(()));
+}
+
+#[test]
+fn generate_repeated_subexpression_once() {
+    let source = "
+    fn main(p: i64) {
+        let mut a: i64 = 1, b: i64 = 2;
+        a = (a + b) + (a + b);
+        b = a + b;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+
+    // The Loads and the inner Add are emitted once in the first assignment, the next statement
+    // loads the variables again after the Store
+    let expected = "
+        %0 = Parameter
+        %1 = Alloc
+        %2 = Constant 1
+         3 Store %2 at %1
+        %4 = Alloc
+        %5 = Constant 2
+         6 Store %5 at %4
+        %7 = Load %1
+        %8 = Load %4
+        %9 = Add %7, %8
+        %10 = Add %9, %9
+         11 Store %10 at %1
+        %12 = Load %1
+        %13 = Load %4
+        %14 = Add %12, %13
+         15 Store %14 at %4
+         16 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_repeated_subexpression_around_call() {
+    let source = "
+    fn foo() -> i64 {
+        return 0;
+    }
+
+    fn main(p: i64) -> i64 {
+        return -p + foo() + -p;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[1]).unwrap();
+
+    // Nothing is reused across the Call
+    let expected = "
+        %0 = Parameter
+        %1 = Neg %0
+        %2 = Call foo, args: 
+        %3 = Add %1, %2
+        %4 = Neg %0
+        %5 = Add %3, %4
+         6 Return %5"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_tail_expression() {
+    let implicit = parse("fn main(p: i64) -> i64 { p + 1 }").unwrap();
+    let explicit = parse("fn main(p: i64) -> i64 { return p + 1; }").unwrap();
+
+    let implicit = generate_instructions(&implicit[0]).unwrap();
+    let explicit = generate_instructions(&explicit[0]).unwrap();
+    assert_eq!(dump(implicit.insts()), dump(explicit.insts()));
+
+    let expected = "
+        %0 = Parameter
+        %1 = Constant 1
+        %2 = Add %0, %1
+         3 Return %2"
+        .to_string();
+    assert_eq!(dump(implicit.insts()), expected);
+}
+
+#[test]
+fn generate_assert() {
+    let funcs = parse(
+        "
+    fn main(p: i64) {
+        assert(p == 1);
+        assert(1 < 2);
+        assert(false);
+    }
+    ",
+    )
+    .unwrap();
+
+    // The condition is negated so the Trap is on the fall through. A condition known at compile
+    // time is either dropped or leaves the Trap only.
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let expected = "
+        %0 = Parameter
+        %1 = Constant 1
+         2 IfFalse %0 != %1, goto 4
+         3 Trap
+         4 Trap
+         5 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_source_lines() {
+    let source = "
+    fn main(x: i64) -> i64 {
+        let mut a: i64 = 1;
+        if (x > 0) {
+            a = x / 2;
+        }
+        return x / a;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    // Each statement keeps its line, an expression has none
+    let lines: Vec<Option<usize>> = funcs[0].stmts().iter().map(Node::line).collect();
+    assert_eq!(lines, [Some(3), Some(4), Some(7)]);
+    match &funcs[0].stmts()[1] {
+        Node::If(_, block, _, _) => assert_eq!(block.line(), Some(4)),
+        _ => panic!("Expected an if"),
+    }
+    match &funcs[0].stmts()[2] {
+        Node::Return(value, _) => assert_eq!(value.line(), None),
+        _ => panic!("Expected a return"),
+    }
+
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let lines: Vec<(String, Option<usize>)> = (0..func.len())
+        .map(|id| {
+            (
+                func[InstId(id)].dump(InstId(id)),
+                func.source_line(InstId(id)),
+            )
+        })
+        .collect();
+    let expected = [
+        ("%0 = Parameter", None),
+        ("%1 = Alloc", Some(3)),
+        ("%2 = Constant 1", Some(3)),
+        (" 3 Store %2 at %1", Some(3)),
+        ("%4 = Constant 0", Some(4)),
+        (" 5 IfFalse %0 > %4, goto 9", Some(4)),
+        ("%6 = Constant 2", Some(5)),
+        ("%7 = Div %0, %6", Some(5)),
+        (" 8 Store %7 at %1", Some(5)),
+        ("%9 = Load %1", Some(7)),
+        ("%10 = Div %0, %9", Some(7)),
+        (" 11 Return %10", Some(7)),
+    ];
+    let expected: Vec<(String, Option<usize>)> = expected
+        .iter()
+        .map(|(dump, line)| (dump.to_string(), *line))
+        .collect();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn generate_while_false() {
+    let source = "
+    fn main() {
+        let mut a: i64 = 0;
+        while (false) {
+            a = a + 1;
+        }
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+
+    // The body of the loop is not generated at all
+    let func = generate_instructions(&funcs[0]).unwrap();
+    let expected = "
+        %0 = Alloc
+        %1 = Constant 0
+         2 Store %1 at %0
+         3 ReturnVoid"
+        .to_string();
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_implicit_return_after_loop() {
+    let last = |source: &str| {
+        let funcs = parse(source).unwrap();
+        let func = generate_instructions(&funcs[0]).unwrap();
+        func.insts().last().unwrap().clone()
+    };
+
+    // No implicit return after a loop which never exits
+    assert!(matches!(
+        last("fn main() { while (true) {} }"),
+        InstData::Goto(_)
+    ));
+    assert!(matches!(
+        last("fn main(p: i64) { if (p == 0) { return; } else { while (1 == 1) {} } }"),
+        InstData::Goto(_)
+    ));
+
+    // Break without a label exits only the inner loop
+    assert!(matches!(
+        last("fn main() { while (true) { while (true) { break; } } }"),
+        InstData::Goto(_)
+    ));
+
+    // Break of the loop needs the implicit return after it
+    assert!(
+        last("fn main() { 'outer: while (true) { while (true) { break 'outer; } } }")
+            == InstData::ReturnVoid
+    );
+    assert!(last("fn main() { while (true) { break; } }") == InstData::ReturnVoid);
+    assert!(
+        last("fn main(p: i64) { while (true) { if (p == 0) { break; } } }") == InstData::ReturnVoid
+    );
+    assert!(last("fn main(p: i64) { while (p == 0) {} }") == InstData::ReturnVoid);
+
+    // Dead code after the return doesn't make the branch fall through: no Goto over the else
+    // branch and no implicit return
+    let funcs =
+        parse("fn main(p: i64) -> i64 { if (p == 0) { return 1; print(2); } else { return 2; } }")
+            .unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+    assert!(func.insts().last().unwrap() == &InstData::Return(InstId(5)));
+    assert!(!func
+        .insts()
+        .iter()
+        .any(|inst| matches!(inst, InstData::Goto(_) | InstData::ReturnVoid)));
+}
+
+#[test]
+fn generate_i32_arithmetic() {
+    let source = "
+    fn main() {
+        let mut x: i32 = 5;
+        x = x + 1;
+        print(x << 31);
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+
+    // Arithmetic of i32 is truncated, the constants fit into i32 and are not converted
+    let expected = "
+        %0 = Alloc
+        %1 = Constant 5
+         2 Store %1 at %0
+        %3 = Load %0
+        %4 = Constant 1
+        %5 = Add %3, %4
+        %6 = Trunc %5
+         7 Store %6 at %0
+        %8 = Load %0
+        %9 = Constant 31
+        %10 = Shl %8, %9
+        %11 = Trunc %10
+        %12 = Call print, args: %11
+         13 ReturnVoid";
+    assert_eq!(dump(func.insts()), expected);
+
+    // Shift amount is limited by the width of i32
+    let source = "
+    fn main() {
+        let mut x: i32 = 1;
+        x = x << 32;
+    }
+    ";
+    let funcs = parse(source).unwrap();
+    assert_eq!(
+        generate_instructions(&funcs[0]).err(),
+        Some("Shift amount 32 is out of range 0..32 at line 4 in function main".to_string())
+    );
+}
+
+#[test]
+fn generate_mixed_widths() {
+    let source = "
+    fn main(a: i64, b: i32) -> i32 {
+        let mut x: i32 = 4294967297;
+        let mut y: i64 = x + a;
+        x = a;
+        if (x < a) {
+            return b;
+        }
+        return y;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+
+    // The literal is wrapped at compile time, i32 is sign extended in the operation with i64
+    // and i64 is truncated when it is assigned to i32. Parameter of i32 is truncated at the entry.
+    let expected = "
+        %0 = Parameter
+        %1 = Parameter
+        %2 = Trunc %1
+        %3 = Alloc
+        %4 = Constant 1
+         5 Store %4 at %3
+        %6 = Alloc
+        %7 = Load %3
+        %8 = SignExtend %7
+        %9 = Add %8, %0
+         10 Store %9 at %6
+        %11 = Trunc %0
+         12 Store %11 at %3
+        %13 = Load %3
+        %14 = SignExtend %13
+         15 IfFalse %14 < %0, goto 17
+         16 Return %2
+        %17 = Load %6
+        %18 = Trunc %17
+         19 Return %18";
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_casts() {
+    let source = "
+    fn main(a: i64) {
+        let mut x: i32 = a as i32;
+        print(x as i64 + a, x as i32, 7 as i32);
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+
+    // Cast to the same width and cast of a literal emit no instructions
+    let expected = "
+        %0 = Parameter
+        %1 = Alloc
+        %2 = Trunc %0
+         3 Store %2 at %1
+        %4 = Load %1
+        %5 = SignExtend %4
+        %6 = Add %5, %0
+        %7 = Call print, args: %6
+        %8 = Load %1
+        %9 = Call print, args: %8
+        %10 = Constant 7
+        %11 = Call print, args: %10
+         12 ReturnVoid";
+    assert_eq!(dump(func.insts()), expected);
+}
+
+#[test]
+fn generate_print_value() {
+    let source = "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = print(p) + 1;
+        print(a);
+        return print(2, a);
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let mut func = generate_instructions(&funcs[0]).unwrap();
+
+    // Uses of print refer to the printed value, in a statement it is not used
+    let expected = "
+        %0 = Parameter
+        %1 = Alloc
+        %2 = Call print, args: %0
+        %3 = Constant 1
+        %4 = Add %0, %3
+         5 Store %4 at %1
+        %6 = Load %1
+        %7 = Call print, args: %6
+        %8 = Constant 2
+        %9 = Call print, args: %8
+        %10 = Load %1
+        %11 = Call print, args: %10
+         12 Return %10";
+    assert_eq!(dump(func.insts()), expected);
+
+    // The calls are lowered to PrintInt which has no value
+    build_intermediate_representation(&mut func).unwrap();
+    assert_eq!(func.verify(), Ok(()));
+    assert!(func[InstId(2)] == InstData::PrintInt(InstId(0)));
+}
+
+#[test]
+fn generate_assigned_parameter() {
+    let source = "
+    fn main(p: i64, q: i32) -> i64 {
+        q = q + 1;
+        return p + q;
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let mut func = generate_instructions(&funcs[0]).unwrap();
+
+    // The assigned parameter is stored to a local variable at the entry, the other one is used
+    // as is
+    let expected = "
+        %0 = Parameter
+        %1 = Parameter
+        %2 = Trunc %1
+        %3 = Alloc
+         4 Store %2 at %3
+        %5 = Load %3
+        %6 = Constant 1
+        %7 = Add %5, %6
+        %8 = Trunc %7
+         9 Store %8 at %3
+        %10 = Load %3
+        %11 = SignExtend %10
+        %12 = Add %0, %11
+         13 Return %12";
+    assert_eq!(dump(func.insts()), expected);
+
+    build_intermediate_representation(&mut func).unwrap();
+    assert_eq!(func.verify(), Ok(()));
+}
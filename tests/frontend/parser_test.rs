@@ -1,7 +1,13 @@
+use ctl::frontend::parser::format_error_at;
 use ctl::frontend::parser::lexing;
+use ctl::frontend::parser::lexing_with_trivia;
 use ctl::frontend::parser::parse;
+use ctl::frontend::parser::parse_all;
+use ctl::frontend::parser::Lexer;
 use ctl::frontend::parser::Node;
+use ctl::frontend::parser::ParseError;
 use ctl::frontend::parser::Token;
+use ctl::frontend::parser::Type;
 
 #[test]
 fn lexical1() {
@@ -53,6 +59,7 @@ fn lexical1() {
         Token::IntLiteral(0),
         Token::Semi,
         Token::RBrace,
+        Token::Eof,
     ];
 
     assert_eq!(tokens, expected);
@@ -94,11 +101,62 @@ fn lexical2() {
         Token::RParent,
         Token::Semi,
         Token::RBrace,
+        Token::Eof,
     ];
 
     assert_eq!(tokens, expected);
 }
 
+#[test]
+fn lexical_eof() {
+    assert_eq!(lexing("").unwrap(), vec![Token::Eof]);
+
+    let tokens = lexing("fn main() {}\n").unwrap();
+    assert_eq!(tokens.len(), 7);
+    assert_eq!(tokens.last(), Some(&Token::Eof));
+}
+
+#[test]
+fn lexical_large_source() {
+    const STATEMENTS: usize = 100_000;
+
+    let mut input = "fn main() {\n    let mut num: i64 = 0;\n".to_string();
+    for i in 0..STATEMENTS {
+        input.push_str(&format!("    num = num + {}; // step {}\n", i, i));
+    }
+    input.push_str("}\n");
+
+    let tokens = lexing(input).unwrap();
+
+    // fn main ( ) { let mut num : i64 = 0 ; then num = num + i ; for each i, } and Eof
+    assert_eq!(tokens.len(), 13 + STATEMENTS * 6 + 2);
+    assert_eq!(tokens[13 + 4], Token::IntLiteral(0));
+    assert_eq!(
+        tokens[13 + (STATEMENTS - 1) * 6 + 4],
+        Token::IntLiteral(STATEMENTS as i64 - 1)
+    );
+    assert_eq!(tokens[tokens.len() - 2], Token::RBrace);
+    assert_eq!(tokens.last(), Some(&Token::Eof));
+}
+
+#[test]
+fn lexical_comment_at_the_end() {
+    let input = "fn main() {} // no line feed after".to_string();
+
+    let tokens = lexing(input).unwrap();
+
+    let expected = vec![
+        Token::Func,
+        Token::Id("main".to_string()),
+        Token::LParent,
+        Token::RParent,
+        Token::LBrace,
+        Token::RBrace,
+        Token::Eof,
+    ];
+    assert_eq!(tokens, expected);
+}
+
 #[test]
 fn parsing_empty_function() {
     let source = "
@@ -128,12 +186,12 @@ fn parsing_arithmetic_sum_literals() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let_ = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
-    let minus = Node::Neg(Box::new(Node::Integer(1)));
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 3);
+    let minus = Node::Integer(-1);
     let lit2 = Node::Integer(2);
 
     let add = Node::Add(Box::new(minus), Box::new(lit2));
-    let assign = Node::Assign("num".to_string(), Box::new(add));
+    let assign = Node::Assign("num".to_string(), Box::new(add), 4);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -157,19 +215,70 @@ fn parsing_arithmetic_sum_with_id() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let1 = Node::Let("num1".to_string(), Box::new(Node::Integer(0)));
-    let let2 = Node::Let("num2".to_string(), Box::new(Node::Integer(0)));
-    let minus = Node::Neg(Box::new(Node::Integer(1)));
+    let let1 = Node::Let("num1".to_string(), Some(Box::new(Node::Integer(0))), 3);
+    let let2 = Node::Let("num2".to_string(), Some(Box::new(Node::Integer(0))), 4);
+    let minus = Node::Integer(-1);
     let add = Node::Add(Box::new(minus), Box::new(Node::Id("num1".to_string())));
 
-    let assign1 = Node::Assign("num1".to_string(), Box::new(Node::Integer(1)));
-    let assign2 = Node::Assign("num2".to_string(), Box::new(add));
+    let assign1 = Node::Assign("num1".to_string(), Box::new(Node::Integer(1)), 6);
+    let assign2 = Node::Assign("num2".to_string(), Box::new(add), 7);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
     assert_eq!(*funcs[0].stmts(), vec![let1, let2, assign1, assign2]);
 }
 
+#[test]
+fn parsing_several_variables_in_one_let() {
+    let source = "
+    fn main() {
+        let mut a: i64 = 0, b: i64 = a + 1;
+        b = a;
+    }
+    ";
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+
+    // Create expected nodes
+    let let_a = Node::Let("a".to_string(), Some(Box::new(Node::Integer(0))), 3);
+    let add = Node::Add(
+        Box::new(Node::Id("a".to_string())),
+        Box::new(Node::Integer(1)),
+    );
+    let let_b = Node::Let("b".to_string(), Some(Box::new(add)), 3);
+    let assign = Node::Assign("b".to_string(), Box::new(Node::Id("a".to_string())), 4);
+
+    // Compare the parsed nodes with the expected ones
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(*funcs[0].stmts(), vec![let_a, let_b, assign]);
+}
+
+#[test]
+fn parsing_negative_literals() {
+    let source = "
+    fn main(p: i64) {
+        let mut num: i64 = -1;
+        num = -(2) - -p;
+    }
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+
+    // Create expected nodes. Only a literal right after the minus becomes a negative constant.
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(-1))), 3);
+    let neg_lit = Node::Neg(Box::new(Node::Integer(2)));
+    let neg_id = Node::Neg(Box::new(Node::Id("p".to_string())));
+    let sub = Node::Sub(Box::new(neg_lit), Box::new(neg_id));
+    let assign = Node::Assign("num".to_string(), Box::new(sub), 4);
+
+    // Compare the parsed nodes with the expected ones
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(*funcs[0].stmts(), vec![let_, assign]);
+}
+
 #[test]
 fn parsing_invalid_assign() {
     let source = "
@@ -181,7 +290,7 @@ fn parsing_invalid_assign() {
     .to_string();
 
     match parse(source) {
-        Err(mes) => assert_eq!(mes, "Use of undeclared variable undeclared".to_string()),
+        Err(mes) => assert_eq!(mes, ParseError::Undeclared("undeclared".to_string())),
         _ => std::unreachable!(),
     };
 }
@@ -200,14 +309,14 @@ fn parsing_arithmetic_mul_sum_literals() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let_ = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 3);
     let lit1 = Node::Integer(1);
     let lit2 = Node::Integer(2);
     let lit3 = Node::Integer(3);
 
     let add = Node::Add(Box::new(lit1), Box::new(lit2));
     let mul = Node::Mul(Box::new(add), Box::new(lit3));
-    let assign = Node::Assign("num".to_string(), Box::new(mul));
+    let assign = Node::Assign("num".to_string(), Box::new(mul), 4);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -228,12 +337,12 @@ fn parsing_arithmetic_div() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let_ = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 3);
     let lit = Node::Integer(2);
     let id = Node::Id("p".to_string());
     let div = Node::Div(Box::new(id), Box::new(lit));
 
-    let assign = Node::Assign("num".to_string(), Box::new(div));
+    let assign = Node::Assign("num".to_string(), Box::new(div), 4);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -254,9 +363,9 @@ fn parsing_assign() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let_ = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 3);
     let int = Node::Integer(0);
-    let assign = Node::Assign("num".to_string(), Box::new(int));
+    let assign = Node::Assign("num".to_string(), Box::new(int), 4);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -280,13 +389,13 @@ fn parsing_shifts() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let1 = Node::Let("num1".to_string(), Box::new(Node::Integer(0)));
-    let let2 = Node::Let("num2".to_string(), Box::new(Node::Integer(0)));
+    let let1 = Node::Let("num1".to_string(), Some(Box::new(Node::Integer(0))), 3);
+    let let2 = Node::Let("num2".to_string(), Some(Box::new(Node::Integer(0))), 4);
     let lshift = Node::Shl(Box::new(Node::Integer(1)), Box::new(Node::Integer(2)));
     let rshift = Node::Shr(Box::new(Node::Integer(2)), Box::new(Node::Integer(1)));
 
-    let assign1 = Node::Assign("num1".to_string(), Box::new(lshift));
-    let assign2 = Node::Assign("num2".to_string(), Box::new(rshift));
+    let assign1 = Node::Assign("num1".to_string(), Box::new(lshift), 6);
+    let assign2 = Node::Assign("num2".to_string(), Box::new(rshift), 7);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -307,7 +416,12 @@ fn parsing_if_one_block() {
 
     // Create expected nodes
     let eq = Node::Eq(Box::new(Node::Integer(0)), Box::new(Node::Integer(0)));
-    let if_stmt = Node::If(Box::new(eq), Box::new(Node::Block(Box::new(vec![]))), None);
+    let if_stmt = Node::If(
+        Box::new(eq),
+        Box::new(Node::Block(Box::default(), 3)),
+        None,
+        3,
+    );
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -328,11 +442,12 @@ fn parsing_if_two_blocks() {
 
     // Create expected nodes
     let eq = Node::Eq(Box::new(Node::Integer(0)), Box::new(Node::Integer(0)));
-    let empty_block = Node::Block(Box::new(vec![]));
+    let empty_block = Node::Block(Box::default(), 3);
     let if_stmt = Node::If(
         Box::new(eq),
         Box::new(empty_block.clone()),
         Some(Box::new(empty_block)),
+        3,
     );
 
     // Compare the parsed nodes with the expected ones
@@ -340,6 +455,70 @@ fn parsing_if_two_blocks() {
     assert_eq!(*funcs[0].stmts(), vec![if_stmt]);
 }
 
+#[test]
+fn parsing_chained_comparisons() {
+    let source = "
+    fn main(a: i64, b: i64, c: i64) {
+        if (a < b >= c) {}
+        if (a == b != c) {}
+    }
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+
+    // Create expected nodes, comparisons are left-associative
+    let id = |name: &str| Box::new(Node::Id(name.to_string()));
+    let empty_block = |line| Box::new(Node::Block(Box::default(), line));
+
+    let lt = Node::Lt(id("a"), id("b"));
+    let ge = Node::Ge(Box::new(lt), id("c"));
+    let if1 = Node::If(Box::new(ge), empty_block(3), None, 3);
+
+    let eq = Node::Eq(id("a"), id("b"));
+    let ne = Node::Ne(Box::new(eq), id("c"));
+    let if2 = Node::If(Box::new(ne), empty_block(4), None, 4);
+
+    // Compare the parsed nodes with the expected ones
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(*funcs[0].stmts(), vec![if1, if2]);
+}
+
+#[test]
+fn parsing_conditions_without_comparison() {
+    let source = "
+    fn main(a: i64, b: i64, flag: i64) {
+        if ((a == b)) {}
+        while (flag) {}
+        while ((true)) {}
+    }
+    "
+    .to_string();
+
+    // Parse source into the AST nodes
+    let funcs = parse(source).unwrap();
+
+    // Create expected nodes
+    let empty_block = |line| Box::new(Node::Block(Box::default(), line));
+    let eq = Node::Eq(
+        Box::new(Node::Id("a".to_string())),
+        Box::new(Node::Id("b".to_string())),
+    );
+    let if_stmt = Node::If(Box::new(eq), empty_block(3), None, 3);
+    let while1 = Node::While(
+        Box::new(Node::Id("flag".to_string())),
+        empty_block(4),
+        None,
+        4,
+    );
+    let while2 = Node::While(Box::new(Node::True), empty_block(5), None, 5);
+
+    // Compare the parsed nodes with the expected ones
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(*funcs[0].stmts(), vec![if_stmt, while1, while2]);
+}
+
 #[test]
 fn parsing_infinite_while_with_break() {
     let source = "
@@ -355,8 +534,8 @@ fn parsing_infinite_while_with_break() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let block = Node::Block(Box::new(vec![Node::Break]));
-    let while_ = Node::While(Box::new(Node::True), Box::new(block));
+    let block = Node::Block(Box::new(vec![Node::Break(None, 4)]), 3);
+    let while_ = Node::While(Box::new(Node::True), Box::new(block), None, 3);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -378,8 +557,8 @@ fn parsing_infinite_while_with_continue() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let block = Node::Block(Box::new(vec![Node::Continue]));
-    let while_ = Node::While(Box::new(Node::True), Box::new(block));
+    let block = Node::Block(Box::new(vec![Node::Continue(None, 4)]), 3);
+    let while_ = Node::While(Box::new(Node::True), Box::new(block), None, 3);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -404,14 +583,14 @@ fn parsing_call_separately() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let1 = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
-    let let2 = Node::Let("other".to_string(), Box::new(Node::Integer(0)));
+    let let1 = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 5);
+    let let2 = Node::Let("other".to_string(), Some(Box::new(Node::Integer(0))), 6);
     let num1 = Node::Integer(1);
     let num2 = Node::Integer(1);
     let add = Node::Add(Box::new(num1), Box::new(num2));
     let id1 = Node::Id("num".to_string());
     let id2 = Node::Id("other".to_string());
-    let call = Node::Call("out".to_string(), Box::new(vec![id1, id2, add]), false);
+    let call = Node::Call("out".to_string(), Box::new(vec![id1, id2, add]), Some(8));
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 2);
@@ -434,11 +613,11 @@ fn parsing_call_as_expression() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let let_ = Node::Let("num".to_string(), Box::new(Node::Integer(0)));
+    let let_ = Node::Let("num".to_string(), Some(Box::new(Node::Integer(0))), 5);
     let num = Node::Integer(1);
-    let call = Node::Call("calc".to_string(), Box::new(vec![]), true);
+    let call = Node::Call("calc".to_string(), Box::default(), None);
     let add = Node::Add(Box::new(call), Box::new(num));
-    let assign = Node::Assign("num".to_string(), Box::new(add));
+    let assign = Node::Assign("num".to_string(), Box::new(add), 6);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 2);
@@ -458,7 +637,7 @@ fn parsing_return_void() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let ret = Node::ReturnVoid;
+    let ret = Node::ReturnVoid(3);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -478,7 +657,7 @@ fn parsing_return() {
     let funcs = parse(source).unwrap();
 
     // Create expected nodes
-    let ret = Node::Return(Box::new(Node::Integer(0)));
+    let ret = Node::Return(Box::new(Node::Integer(0)), 3);
 
     // Compare the parsed nodes with the expected ones
     assert_eq!(funcs.len(), 1);
@@ -496,10 +675,7 @@ fn parsing_return_error1() {
 
     // Parse source into the AST nodes
     match parse(source) {
-        Err(mes) => assert_eq!(
-            mes,
-            "Function with a returning type returns void".to_string()
-        ),
+        Err(mes) => assert_eq!(mes, ParseError::MissingReturnValue { line: 3 }),
         _ => std::unreachable!(),
     };
 }
@@ -514,10 +690,7 @@ fn parsing_return_error2() {
     .to_string();
 
     match parse(source) {
-        Err(mes) => assert_eq!(
-            mes,
-            "Function with no return type returns value: Id<p>".to_string()
-        ),
+        Err(mes) => assert_eq!(mes, ParseError::UnexpectedReturnValue { line: 3 }),
         _ => std::unreachable!(),
     };
 }
@@ -525,7 +698,7 @@ fn parsing_return_error2() {
 #[test]
 fn parsing_two_functions() {
     let source = "
-    fn print(num: i64) {}
+    fn foo(num: i64) {}
     fn main() {}
     "
     .to_string();
@@ -554,7 +727,7 @@ fn parsing_func_does_not_exist() {
 
     let funcs = parse(source);
     match funcs {
-        Err(mes) => assert_eq!(mes, "No function named smth defined".to_string()),
+        Err(mes) => assert_eq!(mes, ParseError::UndefinedFunction("smth".to_string())),
         _ => std::unreachable!(),
     };
 }
@@ -570,14 +743,43 @@ fn parsing_func_print_args_len_differ() {
 
     let funcs = parse(source);
     match funcs {
-        Err(mes) => assert_eq!(
-            mes,
-            "Function print takes 1 argument but 0 was given".to_string()
-        ),
+        Err(mes) => {
+            assert_eq!(
+                mes,
+                ParseError::TooFewArguments {
+                    name: "print".to_string(),
+                    min: 1,
+                    got: 0
+                }
+            );
+            assert_eq!(
+                mes.to_string(),
+                "Function print takes at least 1 argument but 0 were given"
+            );
+        }
         _ => std::unreachable!(),
     };
 }
 
+#[test]
+fn parsing_print_several_args() {
+    let source = "
+    fn main() {
+        print(1, 2);
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+
+    let call = Node::Call(
+        "print".to_string(),
+        Box::new(vec![Node::Integer(1), Node::Integer(2)]),
+        Some(3),
+    );
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(*funcs[0].stmts(), vec![call]);
+}
+
 #[test]
 fn parsing_func_args_len_differ() {
     let source = "
@@ -593,8 +795,917 @@ fn parsing_func_args_len_differ() {
     match funcs {
         Err(mes) => assert_eq!(
             mes,
-            "Function smth takes 1 arguments but 0 was given".to_string()
+            ParseError::ArityMismatch {
+                name: "smth".to_string(),
+                expected: 1,
+                got: 0
+            }
         ),
         _ => std::unreachable!(),
     };
 }
+
+#[test]
+fn parsing_trailing_identifier() {
+    // The function body ends with a lone identifier right before the end of the source
+    assert!(parse("fn main() { x").is_err());
+    assert!(parse("fn main() { let x: i64 = 0; x").is_err());
+}
+
+#[test]
+fn parsing_unexpected_token_after_identifier() {
+    let source = "
+    fn main() {
+        let mut x: i64 = 0;
+        x y;
+    }
+    ";
+
+    match parse(source) {
+        Err(message) => {
+            assert_eq!(
+                message,
+                ParseError::UnexpectedToken {
+                    expected: "'=' or '(' after identifier x".to_string(),
+                    found: Token::Id("y".to_string()),
+                    line: 4,
+                }
+            );
+            assert_eq!(
+                message.to_string(),
+                "line 4: expected '=' or '(' after identifier x but got 'ID<y>'"
+            );
+        }
+        Ok(_) => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn parsing_errors_with_lines() {
+    // Lexer error
+    assert_eq!(
+        lexing("fn main() {\n    let mut a: i64 = 0 # 1;\n}"),
+        Err(ParseError::UnexpectedMark {
+            mark: '#',
+            line: 2,
+            col: 24
+        })
+    );
+
+    // A missing semicolon, lines are counted after a comment too
+    let source = "
+    // comment
+    fn main() {
+        let mut a: i64 = 0
+    }
+    ";
+    match parse(source) {
+        Err(message) => assert_eq!(
+            message,
+            ParseError::UnexpectedToken {
+                expected: "Semi".to_string(),
+                found: Token::RBrace,
+                line: 5,
+            }
+        ),
+        Ok(_) => panic!("Expected a parse error"),
+    }
+
+    // Not a type name
+    match parse("fn main(p: u8) {}") {
+        Err(message) => assert_eq!(
+            message,
+            ParseError::UnexpectedToken {
+                expected: "type name".to_string(),
+                found: Token::Id("u8".to_string()),
+                line: 1,
+            }
+        ),
+        Ok(_) => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn parsing_else_if_chain() {
+    let source = "
+    fn main(p: i64) {
+        if (p == 0) {
+            print(0);
+        } else if (p == 1) {
+            print(1);
+        } else {
+            print(2);
+        }
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(funcs[0].stmts().len(), 1);
+
+    assert_eq!(
+        funcs[0].stmts()[0].to_string(),
+        "IF<Eq<Id<p>,Int<0> >,Block with 1 elements: Call print, args: Int<0> , , > \
+         ELSE IF<Eq<Id<p>,Int<1> >,Block with 1 elements: Call print, args: Int<1> , , > \
+         ELSE<Block with 1 elements: Call print, args: Int<2> , , >"
+    );
+}
+
+#[test]
+fn parsing_all_errors() {
+    let source = "
+    fn main() {
+        let mut a: i64 = 0;
+        a = b;
+        a = 1;
+        c = 2;
+        if (a == 1) {
+            a = 1 +;
+        }
+    }
+    ";
+
+    // Parsing stops at the first error
+    match parse(source) {
+        Err(message) => assert_eq!(message, ParseError::Undeclared("b".to_string())),
+        Ok(_) => panic!("Expected a parse error"),
+    }
+
+    // Every erroneous statement is skipped and reported
+    match parse_all(source) {
+        Err(errors) => assert_eq!(
+            errors,
+            vec![
+                ParseError::Undeclared("b".to_string()),
+                ParseError::Undeclared("c".to_string()),
+                ParseError::UnexpectedToken {
+                    expected: "term".to_string(),
+                    found: Token::Semi,
+                    line: 8,
+                },
+            ]
+        ),
+        Ok(_) => panic!("Expected parse errors"),
+    }
+
+    // Without errors it is the same as parse
+    let funcs = parse_all("fn main() { let mut a: i64 = 0; a = 1; }").unwrap();
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(funcs[0].stmts().len(), 2);
+}
+
+#[test]
+fn parsing_break_continue_outside_of_loop() {
+    let source = "
+    fn main(p: i64) {
+        if (p == 0) {
+            break;
+        }
+    }
+    ";
+    match parse(source) {
+        Err(message) => {
+            assert_eq!(message, ParseError::BreakOutsideLoop { line: 4 });
+            assert_eq!(message.to_string(), "line 4: break outside of loop");
+        }
+        Ok(_) => panic!("Expected a parse error"),
+    }
+
+    let source = "
+    fn main() {
+        while (true) {}
+        continue;
+    }
+    ";
+    match parse(source) {
+        Err(message) => {
+            assert_eq!(message, ParseError::ContinueOutsideLoop { line: 4 });
+            assert_eq!(message.to_string(), "line 4: continue outside of loop");
+        }
+        Ok(_) => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn parsing_break_continue_in_nested_loops() {
+    let source = "
+    fn main(p: i64) {
+        while (p > 0) {
+            while (p > 1) {
+                if (p == 2) {
+                    break;
+                }
+                continue;
+            }
+            break;
+        }
+    }
+    ";
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs[0].stmts().len(), 1);
+}
+
+#[test]
+fn parsing_labeled_loops() {
+    let source = "
+    fn main(p: i64) {
+        'outer: while (true) {
+            while (p > 0) {
+                break 'outer;
+            }
+            continue 'outer;
+        }
+    }
+    ";
+    let funcs = parse(source).unwrap();
+
+    let gt = Node::Gt(
+        Box::new(Node::Id("p".to_string())),
+        Box::new(Node::Integer(0)),
+    );
+    let inner_block = Node::Block(Box::new(vec![Node::Break(Some("outer".to_string()), 5)]), 4);
+    let inner = Node::While(Box::new(gt), Box::new(inner_block), None, 4);
+    let outer_block = Node::Block(
+        Box::new(vec![inner, Node::Continue(Some("outer".to_string()), 7)]),
+        3,
+    );
+    let outer = Node::While(
+        Box::new(Node::True),
+        Box::new(outer_block),
+        Some("outer".to_string()),
+        3,
+    );
+    assert_eq!(*funcs[0].stmts(), vec![outer]);
+
+    // Label which doesn't name any of the enclosing loops
+    let source = "
+    fn main() {
+        'outer: while (true) {}
+        while (true) {
+            break 'outer;
+        }
+    }
+    ";
+    match parse(source) {
+        Err(message) => assert_eq!(
+            message,
+            ParseError::UndeclaredLabel {
+                label: "outer".to_string(),
+                line: 5
+            }
+        ),
+        Ok(_) => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn parsing_call_arguments() {
+    let call = |args: &[i64], line: Option<usize>| {
+        let args = args.iter().map(|v| Node::Integer(*v)).collect();
+        Node::Call("f".to_string(), Box::new(args), line)
+    };
+
+    // Statement and expression positions follow the same rules, a trailing comma is allowed
+    for (args, expected) in [("1, 2", vec![1, 2]), ("", vec![]), ("1, 2,", vec![1, 2])] {
+        let params = if expected.is_empty() {
+            ""
+        } else {
+            "a: i64, b: i64"
+        };
+        let source = format!(
+            "
+            fn f({}) -> i64 {{ return 0; }}
+            fn main() {{
+                let mut x: i64 = f({});
+                f({});
+            }}
+            ",
+            params, args, args
+        );
+        let funcs = parse(source).unwrap();
+        assert_eq!(
+            *funcs[1].stmts(),
+            vec![
+                Node::Let("x".to_string(), Some(Box::new(call(&expected, None))), 4),
+                call(&expected, Some(5)),
+            ]
+        );
+    }
+
+    // A comma without an argument before it is rejected in both positions
+    for stmt in ["f(,);", "let mut x: i64 = f(,);"] {
+        let source = format!("fn f() {{}} fn main() {{ {} }}", stmt);
+        match parse(source) {
+            Err(message) => assert_eq!(
+                message,
+                ParseError::UnexpectedToken {
+                    expected: "term".to_string(),
+                    found: Token::Comma,
+                    line: 1,
+                }
+            ),
+            Ok(_) => panic!("Expected a parse error"),
+        }
+    }
+}
+
+#[test]
+fn parsing_arity_mismatch_in_expression() {
+    let expected = ParseError::ArityMismatch {
+        name: "f".to_string(),
+        expected: 1,
+        got: 2,
+    };
+
+    // Call as a statement and as a part of an expression report the same error
+    let statement = "
+    fn f(p: i64) -> i64 { return p; }
+    fn main() {
+        f(1, 2);
+    }
+    ";
+    let expression = "
+    fn f(p: i64) -> i64 { return p; }
+    fn main() {
+        let mut x: i64 = f(1, 2);
+    }
+    ";
+
+    let mut messages = Vec::new();
+    for source in [statement, expression] {
+        match parse(source) {
+            Err(message) => {
+                assert_eq!(message, expected);
+                messages.push(message.to_string());
+            }
+            Ok(_) => panic!("Expected a parse error"),
+        }
+    }
+    assert_eq!(messages[0], "Function f takes 1 argument but 2 were given");
+    assert_eq!(messages[0], messages[1]);
+
+    let err = parse("fn f(p: i64, q: i64) {} fn main() { f(1); }")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Function f takes 2 arguments but 1 was given"
+    );
+}
+
+#[test]
+fn parsing_let_without_initializer() {
+    let source = "
+    fn main() {
+        let mut a: i64, b: i64 = 1;
+        a = b;
+    }
+    ";
+    let funcs = parse(source).unwrap();
+
+    let let_a = Node::Let("a".to_string(), None, 3);
+    let let_b = Node::Let("b".to_string(), Some(Box::new(Node::Integer(1))), 3);
+    let assign = Node::Assign("a".to_string(), Box::new(Node::Id("b".to_string())), 4);
+    assert_eq!(*funcs[0].stmts(), vec![let_a.clone(), let_b, assign]);
+    assert_eq!(let_a.to_string(), "Let a");
+}
+
+#[test]
+fn lexing_identifiers() {
+    assert_eq!(
+        lexing("_a1 b_2").unwrap(),
+        vec![
+            Token::Id("_a1".to_string()),
+            Token::Id("b_2".to_string()),
+            Token::Eof
+        ]
+    );
+
+    // Only ASCII letters, digits and underscores are allowed
+    for (source, mark, col) in [
+        ("abc\u{e9}", '\u{e9}', 4),
+        ("\u{3b1}", '\u{3b1}', 1),
+        ("a$", '$', 2),
+    ] {
+        assert_eq!(
+            lexing(source),
+            Err(ParseError::UnexpectedMark { mark, line: 1, col })
+        );
+    }
+}
+
+#[test]
+fn parsing_call_argument_types() {
+    let callee = "
+    fn foo(n: i64, flag: bool) -> bool {
+        return flag;
+    }
+    ";
+
+    // Arguments of the declared types: literals, variables, comparisons and calls
+    let source = format!(
+        "{}
+    fn main(p: i64) {{
+        let mut b: bool = p < 0;
+        foo(p + 1, b);
+        foo(2, foo(p, true));
+    }}
+    ",
+        callee
+    );
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs[0].param_types(), &[Type::I64, Type::Bool]);
+    assert_eq!(funcs[0].return_type(), Some(Type::Bool));
+
+    // A bool argument passed to the i64 parameter
+    let source = format!(
+        "{}
+    fn main(p: i64) {{
+        foo(p == 0, true);
+    }}
+    ",
+        callee
+    );
+    match parse(source) {
+        Err(message) => {
+            assert_eq!(
+                message,
+                ParseError::ArgumentTypeMismatch {
+                    name: "foo".to_string(),
+                    index: 0,
+                    expected: Type::I64,
+                    found: Type::Bool,
+                    line: 7,
+                }
+            );
+            assert_eq!(
+                message.to_string(),
+                "line 7: argument 1 of function foo should be i64 but it is bool"
+            );
+        }
+        Ok(_) => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn lexing_comments() {
+    let source = "fn main() {
+    // hello
+    return; // bye
+}";
+
+    assert_eq!(
+        lexing_with_trivia(source).unwrap(),
+        vec![
+            (Token::Func, 1),
+            (Token::Id("main".to_string()), 1),
+            (Token::LParent, 1),
+            (Token::RParent, 1),
+            (Token::LBrace, 1),
+            (Token::COMMENT(" hello".to_string()), 2),
+            (Token::Return, 3),
+            (Token::Semi, 3),
+            (Token::COMMENT(" bye".to_string()), 3),
+            (Token::RBrace, 4),
+            (Token::Eof, 4),
+        ]
+    );
+
+    // Comments are skipped by the regular lexing
+    assert!(!lexing(source)
+        .unwrap()
+        .iter()
+        .any(|t| matches!(t, Token::COMMENT(_))));
+}
+
+#[test]
+fn lexing_trailing_slash() {
+    // A single slash at the end of the input is not a comment
+    assert_eq!(lexing("/").unwrap(), vec![Token::Slash, Token::Eof]);
+    assert_eq!(
+        lexing("a /").unwrap(),
+        vec![Token::Id("a".to_string()), Token::Slash, Token::Eof]
+    );
+    assert_eq!(
+        lexing("a //").unwrap(),
+        vec![Token::Id("a".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn lexing_nul_byte() {
+    // NUL is not the end of the input, the rest is not ignored
+    assert_eq!(
+        lexing("fn main() { return; }\0 garbage ###").unwrap_err(),
+        ParseError::UnexpectedMark {
+            mark: '\0',
+            line: 1,
+            col: 22,
+        }
+    );
+}
+
+#[test]
+fn lexing_error_snippet() {
+    let source = "fn main() {\n    let mut a: i64 = 0;\n    a = 1 # 2;\n}";
+    let err = lexing(source).unwrap_err();
+    let (line, col) = match err {
+        ParseError::UnexpectedMark { line, col, .. } => (line, col),
+        _ => panic!("unexpected error {}", err),
+    };
+    assert_eq!((line, col), (3, 11));
+
+    assert_eq!(
+        format_error_at(source, line, col, &err.to_string()),
+        "line 3: unexpected mark '#'\n  |\n3 |     a = 1 # 2;\n  |           ^\n"
+    );
+
+    // Tabs are kept under the caret
+    assert_eq!(
+        format_error_at("\ta $", 1, 4, "msg"),
+        "msg\n  |\n1 | \ta $\n  | \t  ^\n"
+    );
+
+    // Line and column 0 don't underflow
+    assert_eq!(
+        format_error_at("a $", 0, 0, "msg"),
+        "msg\n  |\n0 | \n  | ^\n"
+    );
+    assert_eq!(
+        format_error_at("a $", 1, 0, "msg"),
+        "msg\n  |\n1 | a $\n  | ^\n"
+    );
+}
+
+#[test]
+fn parsing_tail_expression() {
+    let source = "
+    fn main(a: i64) -> i64 {
+        let mut b: i64 = a;
+        b = b + 1;
+        b * 2
+    }
+    ";
+    let explicit = "
+    fn main(a: i64) -> i64 {
+        let mut b: i64 = a;
+        b = b + 1;
+        return b * 2;
+    }
+    ";
+    assert_eq!(
+        parse(source).unwrap()[0].stmts(),
+        parse(explicit).unwrap()[0].stmts()
+    );
+
+    // Only a single expression, a call is an expression too
+    let funcs = parse("fn f() -> i64 { 1 + 2 } fn g() -> i64 { f() }").unwrap();
+    assert_eq!(
+        *funcs[0].stmts(),
+        vec![Node::Return(
+            Box::new(Node::Add(
+                Box::new(Node::Integer(1)),
+                Box::new(Node::Integer(2))
+            )),
+            1
+        )]
+    );
+    assert_eq!(
+        *funcs[1].stmts(),
+        vec![Node::Return(
+            Box::new(Node::Call("f".to_string(), Box::default(), None)),
+            1
+        )]
+    );
+
+    // A function without a return type has no tail expression
+    assert!(parse("fn main() { 1 }").is_err());
+}
+
+#[test]
+fn parsing_integer_limits() {
+    let funcs = parse(
+        "
+    fn main() -> i64 {
+        let mut min: i64 = -9223372036854775808;
+        return 9223372036854775807;
+    }
+    ",
+    )
+    .unwrap();
+    assert_eq!(
+        *funcs[0].stmts(),
+        vec![
+            Node::Let(
+                "min".to_string(),
+                Some(Box::new(Node::Integer(i64::MIN))),
+                3
+            ),
+            Node::Return(Box::new(Node::Integer(i64::MAX)), 4)
+        ]
+    );
+
+    // 2^63 doesn't fit without the minus
+    assert_eq!(
+        parse("fn main() -> i64 {\n    return 9223372036854775808;\n}").err(),
+        Some(ParseError::IntegerOverflow { line: 2 })
+    );
+    assert_eq!(
+        parse("fn main() -> i64 {\n    return -(9223372036854775808);\n}").err(),
+        Some(ParseError::IntegerOverflow { line: 2 })
+    );
+
+    // Greater than 2^63 is rejected by the lexer
+    assert_eq!(
+        lexing("-9223372036854775809"),
+        Err(ParseError::IntegerOverflow { line: 1 })
+    );
+    assert_eq!(
+        lexing("\n99999999999999999999"),
+        Err(ParseError::IntegerOverflow { line: 2 })
+    );
+}
+
+#[test]
+fn lexing_one_token_at_a_time() {
+    let source = "fn main() {\n    // comment\n    let mut a: i64 = -1;\n}";
+    let mut lexer = Lexer::new(source);
+    assert_eq!(lexer.next(), Some(Ok(Token::Func)));
+    assert_eq!(lexer.line(), 1);
+    assert_eq!(lexer.next(), Some(Ok(Token::Id("main".to_string()))));
+
+    // The rest is the same as from lexing
+    let rest: Vec<Token> = lexer.map(|t| t.unwrap()).collect();
+    assert_eq!(rest, lexing(source).unwrap()[2..]);
+    assert_eq!(rest.last(), Some(&Token::Eof));
+
+    // Nothing after an error
+    let mut lexer = Lexer::new("a # b");
+    assert_eq!(lexer.next(), Some(Ok(Token::Id("a".to_string()))));
+    assert!(matches!(lexer.next(), Some(Err(_))));
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn parsing_assert() {
+    let funcs = parse("fn main(p: i64) { assert(p == 0); }").unwrap();
+    assert_eq!(
+        *funcs[0].stmts(),
+        vec![Node::Call(
+            "assert".to_string(),
+            Box::new(vec![Node::Eq(
+                Box::new(Node::Id("p".to_string())),
+                Box::new(Node::Integer(0))
+            )]),
+            Some(1)
+        )]
+    );
+
+    assert_eq!(
+        parse("fn main() { assert(1 == 1, 2 == 2); }").err(),
+        Some(ParseError::ArityMismatch {
+            name: "assert".to_string(),
+            expected: 1,
+            got: 2
+        })
+    );
+}
+
+#[test]
+fn parsing_assign_in_condition() {
+    let source = "
+    fn main() {
+        let mut a: i64 = 0;
+        if (a = 0) {}
+    }
+    ";
+    let err = parse(source).err().unwrap();
+    assert_eq!(err, ParseError::AssignInCondition { line: 4 });
+    assert_eq!(
+        err.to_string(),
+        "line 4: did you mean '=='? assignment is not allowed in a condition"
+    );
+
+    assert_eq!(
+        parse("fn main() {\n    let mut a: i64 = 0;\n    while (a = 1) {}\n}").err(),
+        Some(ParseError::AssignInCondition { line: 3 })
+    );
+}
+
+#[test]
+fn parsing_truncated_inputs() {
+    // Malformed input gives an error rather than a panic
+    for source in [
+        "/",
+        "fn",
+        "fn f(",
+        "fn f(a",
+        "fn f(a:",
+        "fn f() ->",
+        "fn f() {",
+        "'",
+        "fn f() { a /",
+        // Only functions are allowed at the top level
+        "fn f() {} }",
+    ] {
+        assert!(parse(source).is_err(), "{:?} should not be parsed", source);
+        assert!(
+            parse_all(source).is_err(),
+            "{:?} should not be parsed",
+            source
+        );
+    }
+
+    // Every prefix of a valid program
+    let source = "fn main() -> i64 { let mut a: i64 = 1 / 2; // comment\n 'l: while (a < 3) { break 'l; } a }";
+    for (end, _) in source.char_indices() {
+        let _ = parse(&source[..end]);
+    }
+    assert!(parse(source).is_ok());
+}
+
+#[test]
+fn parsing_i32() {
+    assert_eq!(
+        lexing("i32 i64").unwrap(),
+        vec![Token::I32, Token::I64, Token::Eof]
+    );
+
+    let source = "
+    fn foo(a: i64, b: i32) -> i32 {
+        return b;
+    }
+
+    fn main(p: i32) {
+        let mut x: i32 = 1, y: i64 = 2;
+        x = foo(x, y);
+    }
+    ";
+    let funcs = parse(source).unwrap();
+    assert_eq!(funcs[0].param_types(), &[Type::I64, Type::I32]);
+    assert_eq!(funcs[0].return_type(), Some(Type::I32));
+
+    // Widths of the variables are tracked, integers of different widths may be passed
+    let types = funcs[1].var_types();
+    assert_eq!(types["p"], Type::I32);
+    assert_eq!(types["x"], Type::I32);
+    assert_eq!(types["y"], Type::I64);
+    assert_eq!(Type::I32.width(), Some(32));
+    assert_eq!(Type::Bool.width(), None);
+}
+
+#[test]
+fn parsing_casts() {
+    let source = "
+    fn main(a: i64) {
+        let mut x: i32 = a as i32;
+        x = -a as i32 * 2 as i32;
+    }
+    ";
+    let funcs = parse(source).unwrap();
+
+    let id = || Box::new(Node::Id("a".to_string()));
+    let let_x = Node::Let(
+        "x".to_string(),
+        Some(Box::new(Node::Cast(id(), Type::I32))),
+        3,
+    );
+    // Cast binds tighter than multiplication and looser than the unary minus
+    let assign = Node::Assign(
+        "x".to_string(),
+        Box::new(Node::Mul(
+            Box::new(Node::Cast(Box::new(Node::Neg(id())), Type::I32)),
+            Box::new(Node::Cast(Box::new(Node::Integer(2)), Type::I32)),
+        )),
+        4,
+    );
+    assert_eq!(*funcs[0].stmts(), vec![let_x, assign]);
+
+    // Only integer types are allowed
+    assert_eq!(
+        parse("fn main(a: i64) { print(a as bool); }").err(),
+        Some(ParseError::UnexpectedToken {
+            expected: "integer type".to_string(),
+            found: Token::Bool,
+            line: 1,
+        })
+    );
+}
+
+#[test]
+fn parsing_reserved_names() {
+    let err = parse("fn f(print: i64) {}").err().unwrap();
+    assert_eq!(
+        err,
+        ParseError::ReservedName {
+            name: "print".to_string(),
+            line: 1,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "line 1: print is the name of a built-in function and can't be redefined"
+    );
+
+    let source = "
+    fn main() {
+        let mut a: i64 = 0, assert: bool = true;
+    }
+    ";
+    assert_eq!(
+        parse(source).err(),
+        Some(ParseError::ReservedName {
+            name: "assert".to_string(),
+            line: 3,
+        })
+    );
+
+    // A user function can't replace a built-in one
+    let source = "
+    fn main() {
+        assert(false);
+    }
+
+    fn assert(a: bool) {}
+    ";
+    assert_eq!(
+        parse(source).err(),
+        Some(ParseError::ReservedName {
+            name: "assert".to_string(),
+            line: 6,
+        })
+    );
+}
+
+#[test]
+fn parsing_function_references() {
+    let source = "
+    fn foo() {}
+
+    fn main() {
+        let mut f: i64 = foo;
+        f();
+        print(f(1, main));
+    }
+    ";
+    let funcs = parse(source).unwrap();
+
+    let f = || Box::new(Node::Id("f".to_string()));
+    let expected = vec![
+        Node::Let(
+            "f".to_string(),
+            Some(Box::new(Node::FuncRef("foo".to_string()))),
+            5,
+        ),
+        // A call through a variable doesn't check the arguments
+        Node::CallIndirect(f(), Box::default(), Some(6)),
+        Node::Call(
+            "print".to_string(),
+            Box::new(vec![Node::CallIndirect(
+                f(),
+                Box::new(vec![Node::Integer(1), Node::FuncRef("main".to_string())]),
+                None,
+            )]),
+            Some(7),
+        ),
+    ];
+    assert_eq!(*funcs[1].stmts(), expected);
+
+    // A function defined later and a built-in one can't be referenced
+    let source = "
+    fn main() {
+        let mut f: i64 = bar;
+    }
+
+    fn bar() {}
+    ";
+    assert_eq!(
+        parse(source).err(),
+        Some(ParseError::Undeclared("bar".to_string()))
+    );
+    assert_eq!(
+        parse("fn main() { let mut f: i64 = print; }").err(),
+        Some(ParseError::Undeclared("print".to_string()))
+    );
+}
+
+#[test]
+fn parsing_user_print() {
+    // A user function can't take over the variadic built-in print
+    let source = "
+    fn print(a: i64, b: i64) -> i64 {
+        return a;
+    }
+
+    fn main() {
+        let mut x: i64 = print(5, 3);
+    }
+    ";
+    assert_eq!(
+        parse(source).err(),
+        Some(ParseError::ReservedName {
+            name: "print".to_string(),
+            line: 2,
+        })
+    );
+}
@@ -0,0 +1,109 @@
+use crate::optimizer::ir_constructor::{basic_block, function, get_func, init, inst, Opcode};
+use ctl::optimizer::ir::function::Function;
+use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
+use ctl::optimizer::ir_builder::build_intermediate_representation;
+
+#[test]
+fn terminator_classification() {
+    let terminators = [
+        InstData::IfFalse(InstId(0), InstId(1), Cc::Eq, InstId(2)),
+        InstData::Goto(InstId(0)),
+        InstData::Branch(InstId(0), InstId(1), Cc::Eq),
+        InstData::Jump,
+        InstData::Return(InstId(0)),
+        InstData::ReturnVoid,
+    ];
+    for data in terminators.iter() {
+        assert!(data.is_terminator(), "{} should be a terminator", data);
+    }
+
+    let others = [
+        InstData::Constant(0),
+        InstData::Parameter,
+        InstData::Alloc,
+        InstData::Store(InstId(0), InstId(1)),
+        InstData::Load(InstId(0)),
+        InstData::Add(InstId(0), InstId(1)),
+        InstData::Neg(InstId(0)),
+        InstData::Call("foo".to_string(), None, vec![]),
+    ];
+    for data in others.iter() {
+        assert!(!data.is_terminator(), "{} should not be a terminator", data);
+    }
+}
+
+#[test]
+fn terminator_of_constructed_blocks() {
+    function(
+        init(8, 4),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(0),
+                inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(1)
+                .succs(&[3])
+                .insts(&[inst(3, Opcode::Return).inputs(&[1]), inst(4, Opcode::Jump)]),
+            basic_block(2).insts(&[inst(5, Opcode::Return).inputs(&[0])]),
+            basic_block(3).insts(&[inst(6, Opcode::Alloc)]),
+        ],
+    );
+    let func = get_func();
+
+    assert_eq!(func.blocks()[0].terminator(&func), Some(InstId(2)));
+    assert_eq!(func.blocks()[1].terminator(&func), Some(InstId(4)));
+    assert_eq!(func.blocks()[2].terminator(&func), Some(InstId(5)));
+
+    // Block which is not terminated
+    assert_eq!(func.blocks()[3].terminator(&func), None);
+}
+
+#[test]
+fn iterate_over_block_instructions() {
+    function(
+        init(7, 2),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Alloc),
+                inst(2, Opcode::Store).inputs(&[0]).dest(1),
+                inst(6, Opcode::Jump),
+            ]),
+            basic_block(1).insts(&[
+                inst(3, Opcode::Load).inputs(&[1]),
+                inst(4, Opcode::Neg).inputs(&[3]),
+                inst(5, Opcode::Return).inputs(&[4]),
+            ]),
+        ],
+    );
+    let func = get_func();
+
+    let ids: Vec<InstId> = func.blocks()[0].insts(func.layout()).collect();
+    assert_eq!(ids, vec![InstId(0), InstId(1), InstId(2), InstId(6)]);
+
+    let ids: Vec<InstId> = func.blocks()[1].insts(func.layout()).collect();
+    assert_eq!(ids, vec![InstId(3), InstId(4), InstId(5)]);
+}
+
+#[test]
+fn dump_empty_block() {
+    let mut func = Function::new("main".to_string());
+    func.create_inst(InstData::ReturnVoid);
+    build_intermediate_representation(&mut func).unwrap();
+
+    // A block which is not filled yet
+    let empty = func.create_block();
+    assert!(func.blocks()[empty.0].is_empty());
+    assert!(!func.blocks()[0].is_empty());
+
+    let expected = "Function main:
+
+BB 0: preds: [] succs: []
+ 0 ReturnVoid
+
+BB 1: <empty>
+
+";
+    assert_eq!(func.dump(), expected);
+}
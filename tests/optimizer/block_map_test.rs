@@ -0,0 +1,26 @@
+use ctl::optimizer::ir::basic_block::BlockId;
+use ctl::optimizer::ir::block_map::BlockMap;
+
+#[test]
+fn index_by_block() {
+    let mut map = BlockMap::new(3, 0);
+    assert_eq!(map.len(), 3);
+    map[BlockId(1)] = 5;
+    map[BlockId(2)] += 1;
+    assert_eq!(map, BlockMap::from(vec![0, 5, 1]));
+    assert!(BlockMap::<i64>::from(vec![]).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Block 3 is out of the map of 3 blocks")]
+fn index_out_of_map() {
+    let map = BlockMap::new(3, false);
+    let _ = map[BlockId(3)];
+}
+
+#[test]
+fn enumerate_blocks() {
+    let map = BlockMap::from(vec!['a', 'b']);
+    let pairs: Vec<(BlockId, &char)> = map.iter_enumerated().collect();
+    assert_eq!(pairs, vec![(BlockId(0), &'a'), (BlockId(1), &'b')]);
+}
@@ -0,0 +1,32 @@
+use ctl::frontend::inst_builder::generate_instructions;
+use ctl::frontend::parser::parse;
+use ctl::optimizer::canonicalize::canonicalize;
+use ctl::optimizer::ir::inst::{InstData, InstId};
+
+#[test]
+fn canonicalize_commutative_operations() {
+    let source = "
+    fn main(p0: i64, p1: i64) -> i64 {
+        return (p1 + p0) * (p0 + p1) - (p1 - p0) / (p1 * p0);
+    }
+    ";
+    let funcs = parse(source).unwrap();
+    let mut func = generate_instructions(&funcs[0]).unwrap();
+
+    let p0 = InstId(0);
+    let p1 = InstId(1);
+    assert!(func[InstId(2)] == InstData::Add(p1, p0));
+    assert!(func[InstId(3)] == InstData::Add(p0, p1));
+
+    canonicalize(&mut func);
+
+    // Both Adds are the same computation now
+    assert!(func[InstId(2)] == InstData::Add(p0, p1));
+    assert!(func[InstId(2)] == func[InstId(3)]);
+    assert!(func[InstId(4)] == InstData::Mul(InstId(2), InstId(3)));
+    assert!(func[InstId(6)] == InstData::Mul(p0, p1));
+
+    // Non-commutative operations keep their order
+    assert!(func[InstId(5)] == InstData::Sub(p1, p0));
+    assert!(func[InstId(7)] == InstData::Div(InstId(5), InstId(6)));
+}
@@ -0,0 +1,16 @@
+//! Helpers shared by the tests of the optimizer
+
+use ctl::frontend::inst_builder::generate_instructions;
+use ctl::frontend::parser::parse;
+use ctl::optimizer::ir::function::Function;
+use ctl::optimizer::ir_builder::build_intermediate_representation;
+
+/// Builds the control flow graph of the first function of the source and verifies it.
+pub fn build(source: &str) -> Function {
+    let funcs = parse(source).unwrap();
+    let mut func = generate_instructions(&funcs[0]).unwrap();
+    build_intermediate_representation(&mut func).unwrap();
+    func.verify().unwrap();
+
+    func
+}
@@ -0,0 +1,58 @@
+use crate::optimizer::common::build;
+use crate::optimizer::ir_constructor::{basic_block, function, get_func, init, inst, Opcode};
+use ctl::optimizer::fold_branches::fold_branches;
+use ctl::optimizer::ir::basic_block::BlockId;
+use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
+
+#[test]
+fn fold_branch_on_constants() {
+    // if (2 > 1) { print(p); } else { print(0); }
+    // with the literals which became Constants after the AST, e.g. specialized parameters. The
+    // Constants are in the entry block which dominates their uses.
+    function(
+        init(11, 4),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(2),
+                inst(2, Opcode::Constant).value(1),
+                inst(6, Opcode::Constant).value(0),
+                inst(3, Opcode::Branch).inputs(&[1, 2]).cc(Cc::Gt),
+            ]),
+            basic_block(1).succs(&[3]).insts(&[
+                inst(4, Opcode::PrintInt).inputs(&[0]),
+                inst(5, Opcode::Jump),
+            ]),
+            basic_block(2).succs(&[3]).insts(&[
+                inst(7, Opcode::PrintInt).inputs(&[6]),
+                inst(8, Opcode::Jump),
+            ]),
+            basic_block(3).insts(&[
+                inst(9, Opcode::Cmp).inputs(&[2, 6]).cc(Cc::Eq),
+                inst(10, Opcode::Return).inputs(&[9]),
+            ]),
+        ],
+    );
+    let mut func = get_func();
+
+    fold_branches(&mut func);
+    assert_eq!(func.verify(), Ok(()));
+
+    // Then-path is the only successor, the else block has no predecessors
+    assert!(matches!(func[InstId(3)], InstData::Jump));
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1)]);
+    assert!(func.preds_of(BlockId(2)).is_empty());
+    assert_eq!(func.preds_of(BlockId(3)), &[BlockId(1), BlockId(2)]);
+
+    // 1 == 0 is false
+    assert!(func[InstId(9)] == InstData::Constant(0));
+}
+
+#[test]
+fn keep_branch_on_parameter() {
+    let mut func = build("fn main(p: i64) { if (p > 1) { print(p); } }");
+
+    let before = func.clone();
+    fold_branches(&mut func);
+    assert_eq!(func, before);
+}
@@ -0,0 +1,1009 @@
+use crate::optimizer::common::build;
+use crate::optimizer::ir_constructor::{basic_block, function, get_func, init, inst, Opcode};
+use ctl::optimizer::ir::basic_block::{BasicBlock, BlockId, InstNode};
+use ctl::optimizer::ir::function::{escape, DumpOptions, Function};
+use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
+use ctl::optimizer::ir_builder::build_intermediate_representation;
+
+// Tests on the methods of Function which change the instruction layout
+
+/// fn main(p: i64) -> i64 {
+///     return p;
+/// }
+#[test]
+fn insert_constant_before_return() {
+    let mut func = Function::new("main".to_string());
+
+    func.create_inst(InstData::Parameter);
+    let ret = func.create_inst(InstData::Return(InstId(0)));
+
+    build_intermediate_representation(&mut func).unwrap();
+
+    let constant = func.insert_before(ret, InstData::Constant(1));
+    assert_eq!(constant, InstId(2));
+    func[ret] = InstData::Return(constant);
+
+    let expected = "Function main:
+
+BB 0: preds: [] succs: []
+%0 = Parameter
+%2 = Constant 1
+ 1 Return %2
+
+"
+    .to_string();
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn insert_before_first_instruction() {
+    let mut func = Function::new("main".to_string());
+
+    let ret = func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func).unwrap();
+
+    let param = func.insert_before(ret, InstData::Parameter);
+    assert_eq!(*func.blocks()[0].first(), Some(param));
+    assert_eq!(*func.blocks()[0].last(), Some(ret));
+    assert_eq!(*func.layout()[ret.0].prev(), Some(param));
+
+    let expected = "Function main:
+
+BB 0: preds: [] succs: []
+%1 = Parameter
+ 0 ReturnVoid
+
+"
+    .to_string();
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn insert_after_last_instruction() {
+    let mut func = Function::new("main".to_string());
+
+    func.create_inst(InstData::Parameter);
+    let param = func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func).unwrap();
+
+    // Insert in the middle and then after the last one
+    let alloc = func.insert_after(param, InstData::Alloc);
+    let ret = func.blocks()[0].last().unwrap();
+    let jump = func.insert_after(ret, InstData::Jump);
+
+    assert_eq!(*func.blocks()[0].last(), Some(jump));
+    assert_eq!(*func.layout()[jump.0].prev(), Some(ret));
+    assert_eq!(*func.layout()[alloc.0].prev(), Some(param));
+
+    let expected = "Function main:
+
+BB 0: preds: [] succs: []
+%0 = Parameter
+%1 = Parameter
+%3 = Alloc
+ 2 ReturnVoid
+ 4 Jump
+
+"
+    .to_string();
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn check_returns_on_both_branches() {
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        if (p > 0) {
+            return p;
+        } else {
+            return p + 1;
+        }
+    }
+    ",
+    );
+
+    assert!(func.has_return_type());
+    assert_eq!(func.check_returns(func.has_return_type()), Ok(()));
+
+    // There is no dead implicit return after the branches
+    assert_eq!(func.blocks().len(), 3);
+    assert!(!func
+        .insts()
+        .iter()
+        .any(|data| matches!(data, InstData::ReturnVoid | InstData::Jump)));
+}
+
+#[test]
+fn check_returns_on_one_branch() {
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        if (p > 0) {
+            return p;
+        }
+    }
+    ",
+    );
+
+    assert_eq!(
+        func.check_returns(func.has_return_type()),
+        Err("Function main may reach its end without returning a value".to_string())
+    );
+}
+
+#[test]
+fn check_returns_without_return_type() {
+    let func = build(
+        "
+    fn main(p: i64) {
+        if (p > 0) {}
+    }
+    ",
+    );
+
+    assert!(!func.has_return_type());
+    assert_eq!(func.check_returns(func.has_return_type()), Ok(()));
+}
+
+#[test]
+fn constants_sorted_by_value() {
+    let source = "
+    fn main(p: i64) -> i64 {
+        return p * 7 + 100 - -3 + 7 + 0;
+    }
+    ";
+
+    let expected = vec![
+        (-3, InstId(5)),
+        (0, InstId(8)),
+        (7, InstId(1)),
+        (100, InstId(3)),
+    ];
+
+    // Every build gives the same order regardless of the hashing of the constants map
+    for _ in 0..10 {
+        let func = build(source);
+        assert_eq!(func.constants().len(), 4);
+        assert_eq!(func.constants_sorted(), expected);
+    }
+}
+
+#[test]
+fn real_len_skips_placeholders() {
+    // Instructions 2 and 4 are not created, their slots stay Invalid
+    function(
+        init(6, 2),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Return).inputs(&[0]),
+            ]),
+            basic_block(1).insts(&[inst(3, Opcode::Parameter), inst(5, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    let func = get_func();
+    assert_eq!(func.len(), 6);
+    assert_eq!(func.real_len(), 4);
+}
+
+#[test]
+fn entry_and_exit_blocks() {
+    // Both paths join in the block with the implicit return
+    let func = build(
+        "
+    fn main(p: i64) {
+        let mut a: i64 = 0;
+        if (p > 0) {
+            a = 1;
+        } else {
+            a = 2;
+        }
+    }
+    ",
+    );
+    assert_eq!(func.blocks().len(), 4);
+    assert_eq!(func.entry_block(), BlockId(0));
+    assert_eq!(func.exit_blocks(), vec![BlockId(3)]);
+
+    // Every path has its own return
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        if (p > 0) {
+            return p;
+        } else {
+            return p + 1;
+        }
+    }
+    ",
+    );
+    assert_eq!(func.entry_block(), BlockId(0));
+    assert_eq!(func.exit_blocks(), vec![BlockId(1), BlockId(2)]);
+}
+
+#[test]
+fn succs_and_preds_of_blocks() {
+    let func = build(
+        "
+    fn main(p: i64) {
+        let mut a: i64 = 0;
+        if (p > 0) {
+            a = 1;
+        } else {
+            a = 2;
+        }
+    }
+    ",
+    );
+
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1), BlockId(2)]);
+    assert_eq!(func.succs_of(BlockId(1)), &[BlockId(3)]);
+    assert_eq!(func.succs_of(BlockId(2)), &[BlockId(3)]);
+    assert!(func.succs_of(BlockId(3)).is_empty());
+
+    assert!(func.preds_of(BlockId(0)).is_empty());
+    assert_eq!(func.preds_of(BlockId(1)), &[BlockId(0)]);
+    assert_eq!(func.preds_of(BlockId(2)), &[BlockId(0)]);
+    assert_eq!(func.preds_of(BlockId(3)), &[BlockId(1), BlockId(2)]);
+}
+
+/// fn main(p: i64) -> i64 {
+///     let mut a: i64;
+///     if (p == 0) {
+///         a = 0;
+///     }
+///     return a;
+/// }
+fn build_assigned_in_one_branch() -> Function {
+    function(
+        init(10, 4),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Alloc),
+                inst(2, Opcode::Constant).value(0),
+                inst(3, Opcode::Branch).inputs(&[0, 2]).cc(Cc::Eq),
+            ]),
+            basic_block(1).succs(&[3]).insts(&[
+                inst(4, Opcode::Store).inputs(&[2]).dest(1),
+                inst(5, Opcode::Jump),
+            ]),
+            basic_block(2).succs(&[3]).insts(&[inst(7, Opcode::Jump)]),
+            basic_block(3).insts(&[
+                inst(8, Opcode::Load).inputs(&[1]),
+                inst(9, Opcode::Return).inputs(&[8]),
+            ]),
+        ],
+    );
+
+    get_func()
+}
+
+/// fn main(p: i64) -> i64 {
+///     let mut a: i64;
+///     if (p == 0) {
+///         a = 0;
+///     } else {
+///         a = p;
+///     }
+///     return a;
+/// }
+fn build_assigned_in_both_branches() -> Function {
+    function(
+        init(10, 4),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Alloc),
+                inst(2, Opcode::Constant).value(0),
+                inst(3, Opcode::Branch).inputs(&[0, 2]).cc(Cc::Eq),
+            ]),
+            basic_block(1).succs(&[3]).insts(&[
+                inst(4, Opcode::Store).inputs(&[2]).dest(1),
+                inst(5, Opcode::Jump),
+            ]),
+            basic_block(2).succs(&[3]).insts(&[
+                inst(6, Opcode::Store).inputs(&[0]).dest(1),
+                inst(7, Opcode::Jump),
+            ]),
+            basic_block(3).insts(&[
+                inst(8, Opcode::Load).inputs(&[1]),
+                inst(9, Opcode::Return).inputs(&[8]),
+            ]),
+        ],
+    );
+
+    get_func()
+}
+
+#[test]
+fn check_initialized_on_one_branch() {
+    let func = build_assigned_in_one_branch();
+    assert_eq!(
+        func.check_initialized(),
+        Err("Variable %1 is possibly uninitialized at instruction 8 in function ".to_string())
+    );
+}
+
+#[test]
+fn check_initialized_on_both_branches() {
+    let func = build_assigned_in_both_branches();
+    assert_eq!(func.check_initialized(), Ok(()));
+
+    // A variable stored before the loop is initialized inside of it
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = 0;
+        while (a < p) {
+            a = a + 1;
+        }
+        return a;
+    }
+    ",
+    );
+    assert_eq!(func.check_initialized(), Ok(()));
+}
+
+/// Loop on a variable which initial Store is in the preheader (BB 0) before the Load in the
+/// header (BB 1).
+fn build_loop_variable_stored_before_loop() -> Function {
+    function(
+        init(8, 4),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Alloc),
+                inst(1, Opcode::Constant).value(0),
+                inst(2, Opcode::Store).inputs(&[1]).dest(0),
+                inst(3, Opcode::Jump),
+            ]),
+            basic_block(1).succs(&[2, 3]).insts(&[
+                inst(4, Opcode::Load).inputs(&[0]),
+                inst(5, Opcode::Branch).inputs(&[4, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(2).succs(&[1]).insts(&[inst(6, Opcode::Jump)]),
+            basic_block(3).insts(&[inst(7, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    get_func()
+}
+
+/// The same loop with the initial Store misplaced into the loop body (BB 2) after the Load in
+/// the header (BB 1).
+fn build_loop_variable_stored_in_body() -> Function {
+    function(
+        init(8, 4),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Alloc),
+                inst(1, Opcode::Constant).value(0),
+                inst(3, Opcode::Jump),
+            ]),
+            basic_block(1).succs(&[2, 3]).insts(&[
+                inst(4, Opcode::Load).inputs(&[0]),
+                inst(5, Opcode::Branch).inputs(&[4, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(2).succs(&[1]).insts(&[
+                inst(2, Opcode::Store).inputs(&[1]).dest(0),
+                inst(6, Opcode::Jump),
+            ]),
+            basic_block(3).insts(&[inst(7, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    get_func()
+}
+
+#[test]
+fn check_initialized_loop_variable() {
+    // The Store in the preheader reaches the Load on the entry to the loop and on the back edge
+    let func = build_loop_variable_stored_before_loop();
+    assert_eq!(func.check_initialized(), Ok(()));
+
+    // The Store in the body doesn't dominate the Load in the header, it is found uninitialized
+    let func = build_loop_variable_stored_in_body();
+    assert_eq!(
+        func.check_initialized(),
+        Err("Variable %0 is possibly uninitialized at instruction 4 in function ".to_string())
+    );
+}
+
+#[test]
+fn control_flow_graph_to_dot() {
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        if (p > 0) {
+            return p;
+        }
+        return 0;
+    }
+    ",
+    );
+
+    let expected = r#"digraph "main" {
+    node [shape=box];
+    bb0 [label="BB 0\l%0 = Parameter\l%1 = Constant 0\l 2 Branch %0 > %1\l"];
+    bb1 [label="BB 1\l 3 Return %0\l"];
+    bb2 [label="BB 2\l 4 Return %1\l"];
+    bb0 -> bb1 [label=true];
+    bb0 -> bb2 [label=false];
+}
+"#;
+    assert_eq!(func.to_dot(), expected);
+}
+
+#[test]
+fn escape_names() {
+    assert_eq!(escape("main"), "main");
+    assert_eq!(escape(r#"a"b"#), r#"a\"b"#);
+    assert_eq!(escape(r"a\b"), r"a\\b");
+    assert_eq!(escape("a\nb\tc"), r"a\nb\tc");
+    assert_eq!(escape("\u{1}"), r"\u0001");
+
+    // A name which breaks the quotes is escaped in the DOT output
+    let mut func = Function::new(r#"say "hi""#.to_string());
+    func.create_inst(InstData::ReturnVoid);
+    build_intermediate_representation(&mut func).unwrap();
+    assert!(func.to_dot().starts_with(r#"digraph "say \"hi\"" {"#));
+}
+
+#[test]
+fn verify_store_to_alloc() {
+    // fn main(p: i64) {
+    //     let mut a: i64 = p;
+    // }
+    let mut func = Function::new("main".to_string());
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Alloc);
+    let store = func.create_inst(InstData::Store(InstId(0), InstId(1)));
+    func.create_inst(InstData::ReturnVoid);
+    build_intermediate_representation(&mut func).unwrap();
+    assert_eq!(func.verify(), Ok(()));
+
+    // Store to the parameter
+    func[store] = InstData::Store(InstId(1), InstId(0));
+    assert_eq!(
+        func.verify(),
+        Err("Function main: instruction 2 accesses %0 which is not an Alloc".to_string())
+    );
+}
+
+#[test]
+fn verify_control_flow() {
+    function(
+        init(4, 3),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Branch).inputs(&[0, 0]).cc(Cc::Eq),
+            ]),
+            basic_block(1).succs(&[2]).insts(&[inst(2, Opcode::Jump)]),
+            basic_block(2).insts(&[inst(3, Opcode::ReturnVoid)]),
+        ],
+    );
+    let mut func = get_func();
+    assert_eq!(func.verify(), Ok(()));
+
+    // A predecessor is lost
+    let mut block = BasicBlock::new();
+    *block.first_mut() = *func.blocks()[2].first();
+    *block.last_mut() = *func.blocks()[2].last();
+    block.add_pred(BlockId(0));
+    func.blocks_mut()[2] = block;
+    assert_eq!(
+        func.verify(),
+        Err("Function : BB 2 is a successor of BB 1 but not its predecessor".to_string())
+    );
+}
+
+#[test]
+fn clone_function() {
+    let mut func = build(
+        "
+    fn main(p: i64) -> i64 {
+        return p + 1;
+    }
+    ",
+    );
+
+    let before = func.clone();
+    assert!(before == func);
+    assert_eq!(before.dump(), func.dump());
+
+    // Changes of the function don't affect its copy
+    let ret = func.blocks()[0].last().unwrap();
+    func.insert_before(ret, InstData::Alloc);
+    assert!(before != func);
+    assert_eq!(before.len() + 1, func.len());
+    assert!(!before.dump().contains("Alloc"));
+}
+
+#[test]
+fn rebuild_predecessors_from_successors() {
+    let mut func = build(
+        "
+    fn main(p: i64) {
+        let mut a: i64 = 0;
+        while (a < p) {
+            if (a == 2) {
+                a = 3;
+            }
+            a = a + 1;
+        }
+    }
+    ",
+    );
+    let preds: Vec<Vec<BlockId>> = func.blocks().iter().map(|b| b.preds().to_vec()).collect();
+
+    // Lose some of the predecessors and add a wrong one
+    func.blocks_mut()[1].preds_mut().clear();
+    func.blocks_mut()[2].preds_mut().reverse();
+    func.blocks_mut()[0].add_pred(BlockId(3));
+    assert!(func.verify().is_err());
+
+    func.rebuild_predecessors();
+    assert_eq!(func.verify(), Ok(()));
+    for (block, expected) in func.blocks().iter().zip(preds.iter()) {
+        assert_eq!(block.preds(), &expected[..]);
+    }
+}
+
+#[test]
+fn equal_functions() {
+    let source = "
+    fn main(p: i64) {
+        if (p > 0) {
+            print(p);
+        }
+    }
+    ";
+    let built = build(source);
+    assert_eq!(built, build(source));
+
+    // The same graph made by the constructor, the slots which are not in the blocks don't matter
+    let construct = |insts_len| {
+        function(
+            init(insts_len, 3),
+            &[
+                basic_block(0).succs(&[1, 2]).insts(&[
+                    inst(0, Opcode::Parameter),
+                    inst(1, Opcode::Constant).value(0),
+                    inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Gt),
+                ]),
+                basic_block(1).succs(&[2]).insts(&[
+                    inst(3, Opcode::PrintInt).inputs(&[0]),
+                    inst(5, Opcode::Jump),
+                ]),
+                basic_block(2).insts(&[inst(4, Opcode::ReturnVoid)]),
+            ],
+        );
+        get_func()
+    };
+    assert_eq!(construct(6), construct(9));
+
+    // Different name
+    assert_ne!(built, build(&source.replace("fn main", "fn foo")));
+
+    // Different condition code
+    let mut other = build(source);
+    other[InstId(2)] = InstData::Branch(InstId(0), InstId(1), Cc::Ge);
+    assert_ne!(built, other);
+
+    // Different control flow
+    let other = build("fn main(p: i64) { print(p); }");
+    assert_ne!(built, other);
+}
+
+#[test]
+fn dump_hex_constants() {
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        return p * 255;
+    }
+    ",
+    );
+
+    let decimal = "Function main:
+
+BB 0: preds: [] succs: []
+%0 = Parameter
+%1 = Constant 255
+%2 = Mul %0, %1
+ 3 Return %2
+
+";
+    assert_eq!(func.dump(), decimal);
+    assert_eq!(func.dump_with(DumpOptions::default()), decimal);
+
+    let hex = func.dump_with(DumpOptions {
+        hex_constants: true,
+    });
+    assert_eq!(hex, decimal.replace("Constant 255", "Constant 0xff"));
+}
+
+#[test]
+fn reachable_exit() {
+    let func = build(
+        "
+    fn main() {
+        let mut a: i64 = 0;
+        while (true) {
+            a = a + 1;
+        }
+    }
+    ",
+    );
+    // Control never falls through the loop so there is no implicit return
+    assert!(func.exit_blocks().is_empty());
+    assert!(!func.has_reachable_exit());
+
+    let func = build(
+        "
+    fn main(p: i64) {
+        while (true) {
+            if (p == 0) {
+                break;
+            }
+        }
+    }
+    ",
+    );
+    assert!(func.has_reachable_exit());
+}
+
+#[test]
+fn display_function() {
+    let func = build(
+        "
+    fn main(p: i64) -> i64 {
+        return p + 1;
+    }
+    ",
+    );
+    assert_eq!(format!("{}", func), func.dump());
+}
+
+#[test]
+fn invert_branch() {
+    let original = build(
+        "
+    fn main(a: i64, b: i64) -> i64 {
+        if (a < b) {
+            return 1;
+        }
+        return 2;
+    }
+    ",
+    );
+    // The fall through of IfFalse is the true successor
+    let last = original.blocks()[0].last().unwrap();
+    assert!(matches!(original[last], InstData::Branch(_, _, Cc::Lt)));
+    assert_eq!(original.succs_of(BlockId(0)), &[BlockId(1), BlockId(2)]);
+
+    let mut func = original.clone();
+    func.invert_branch(BlockId(0));
+    assert!(matches!(func[last], InstData::Branch(_, _, Cc::Ge)));
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(2), BlockId(1)]);
+    assert!(func.verify().is_ok());
+
+    func.invert_branch(BlockId(0));
+    assert_eq!(func, original);
+}
+
+#[test]
+fn while_false_is_absent() {
+    let func = build(
+        "
+    fn main(p: i64) {
+        let mut a: i64 = p;
+        while (false) {
+            a = a + 1;
+            print(a);
+        }
+        print(a);
+    }
+    ",
+    );
+
+    // A single block with the statements around the loop and without its body
+    let expected = "Function main:
+
+BB 0: preds: [] succs: []
+%0 = Parameter
+%1 = Alloc
+ 2 Store %0 at %1
+%3 = Load %1
+ 4 PrintInt %3
+ 5 ReturnVoid
+
+";
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn split_arc_with_new_block() {
+    let mut func = build(
+        "
+    fn main(p: i64) {
+        if (p == 0) {
+            print(p);
+        }
+    }
+    ",
+    );
+    // Arc 0 -> 2 is critical: 0 has two successors and 2 has two predecessors
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1), BlockId(2)]);
+    assert_eq!(func.preds_of(BlockId(2)), &[BlockId(0), BlockId(1)]);
+
+    let block = func.create_block_after(BlockId(0), BlockId(2));
+    assert_eq!(block, BlockId(3));
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1), BlockId(3)]);
+    assert_eq!(func.preds_of(BlockId(3)), &[BlockId(0)]);
+    assert_eq!(func.succs_of(BlockId(3)), &[BlockId(2)]);
+    assert_eq!(func.preds_of(BlockId(2)), &[BlockId(3), BlockId(1)]);
+
+    let last = func.blocks()[3].last().unwrap();
+    assert!(matches!(func[last], InstData::Jump));
+    assert_eq!(func.verify(), Ok(()));
+}
+
+#[test]
+fn split_missing_arc() {
+    let mut func = build("fn main(p: i64) { if (p == 0) { print(p); } }");
+    let before = func.clone();
+
+    // There is no arc from 1 to 0, the function is left as it was
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        func.create_block_after(BlockId(1), BlockId(0))
+    }));
+    assert!(result.is_err());
+    assert_eq!(func, before);
+    assert_eq!(func.blocks().len(), 3);
+    assert_eq!(func.len(), before.len());
+}
+
+#[test]
+fn store_undef_on_uninitialized_path() {
+    let mut func = build(
+        "
+    fn main(p: i64) -> i64 {
+        let mut a: i64;
+        if (p == 0) {
+            a = 1;
+        }
+        return a;
+    }
+    ",
+    );
+    assert!(func.check_initialized().is_err());
+
+    func.store_undef_on_uninitialized_path();
+    assert_eq!(func.check_initialized(), Ok(()));
+    assert_eq!(func.verify(), Ok(()));
+
+    // The merge in BB 2 gets Undef from BB 0 where the branch around the assignment starts
+    let expected = "Function main:
+
+BB 0: preds: [] succs: [1, 2]
+%0 = Parameter
+%1 = Alloc
+%2 = Constant 0
+%9 = Undef
+ 10 Store %9 at %1
+ 3 Branch %0 == %2
+
+BB 1: preds: [0] succs: [2]
+%4 = Constant 1
+ 5 Store %4 at %1
+ 8 Jump
+
+BB 2: preds: [0, 1] succs: []
+%6 = Load %1
+ 7 Return %6
+
+";
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn store_undef_once_per_predecessor() {
+    // BB 4 is the uninitialized predecessor of both BB 2 and BB 3. BB 5 is an empty block
+    // without a terminator like the ones left by if_conversion.
+    function(
+        init(13, 6),
+        &[
+            basic_block(0).succs(&[1, 4]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Alloc),
+                inst(2, Opcode::Alloc),
+                inst(3, Opcode::Constant).value(0),
+                inst(4, Opcode::Branch).inputs(&[0, 3]).cc(Cc::Eq),
+            ]),
+            basic_block(1).succs(&[2, 3]).insts(&[
+                inst(5, Opcode::Store).inputs(&[3]).dest(1),
+                inst(6, Opcode::Store).inputs(&[3]).dest(2),
+                inst(7, Opcode::Branch).inputs(&[0, 3]).cc(Cc::Ne),
+            ]),
+            basic_block(2).insts(&[
+                inst(8, Opcode::Load).inputs(&[1]),
+                inst(9, Opcode::Return).inputs(&[8]),
+            ]),
+            basic_block(3).insts(&[
+                inst(10, Opcode::Load).inputs(&[2]),
+                inst(11, Opcode::Return).inputs(&[10]),
+            ]),
+            basic_block(4)
+                .succs(&[2, 3])
+                .insts(&[inst(12, Opcode::Branch).inputs(&[0, 3]).cc(Cc::Lt)]),
+            basic_block(5).succs(&[2]),
+        ],
+    );
+    let mut func = get_func();
+
+    func.store_undef_on_uninitialized_path();
+    assert_eq!(func.check_initialized(), Ok(()));
+
+    // One Store of Undef per variable at the end of BB 4
+    let stores: Vec<String> = func.blocks()[4]
+        .insts(func.layout())
+        .map(|id| func[id].to_string())
+        .collect();
+    assert_eq!(
+        stores,
+        [
+            "Undef",
+            "Store %13 at %1",
+            "Undef",
+            "Store %15 at %2",
+            "Branch %0 < %3"
+        ]
+    );
+}
+
+#[test]
+fn remove_middle_block() {
+    let mut func = build(
+        "
+    fn main(p: i64) {
+        while (p == 0) {
+            print(p);
+        }
+        print(1);
+    }
+    ",
+    );
+    // 0 -> 1 (header) -> 2 (body) -> 1, 1 -> 3 (exit)
+    assert_eq!(func.blocks().len(), 4);
+    assert_eq!(func.succs_of(BlockId(1)), &[BlockId(2), BlockId(3)]);
+    assert_eq!(func.preds_of(BlockId(1)), &[BlockId(0), BlockId(2)]);
+    let body: Vec<InstId> = func.blocks()[2].insts(func.layout()).collect();
+    let exit: Vec<InstId> = func.blocks()[3].insts(func.layout()).collect();
+
+    func.remove_block(BlockId(2));
+
+    // The exit block is renumbered from 3 to 2, the arcs of the body are gone
+    assert_eq!(func.blocks().len(), 3);
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1)]);
+    assert_eq!(func.preds_of(BlockId(1)), &[BlockId(0)]);
+    assert_eq!(func.succs_of(BlockId(1)), &[BlockId(2)]);
+    assert_eq!(func.preds_of(BlockId(2)), &[BlockId(1)]);
+
+    // Instructions of the body are unlinked, the ones of the exit block point to its new id
+    for inst in body {
+        assert!(func.layout()[inst.0] == InstNode::new());
+    }
+    assert_eq!(
+        func.blocks()[2].insts(func.layout()).collect::<Vec<_>>(),
+        exit
+    );
+    for inst in exit {
+        assert_eq!(func.layout()[inst.0].block(), BlockId(2));
+    }
+}
+
+#[test]
+fn instruction_positions() {
+    // Instructions are placed out of the order of their ids
+    function(
+        init(7, 2),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Alloc),
+                inst(3, Opcode::Store).inputs(&[0]).dest(1),
+                inst(2, Opcode::Load).inputs(&[1]),
+                inst(4, Opcode::Jump),
+            ]),
+            basic_block(1).insts(&[
+                inst(6, Opcode::Constant).value(1),
+                inst(5, Opcode::ReturnVoid),
+            ]),
+        ],
+    );
+    let func = get_func();
+
+    assert_eq!(func.instruction_index(InstId(0)), (BlockId(0), 0));
+    assert_eq!(func.instruction_index(InstId(3)), (BlockId(0), 2));
+    assert_eq!(func.instruction_index(InstId(2)), (BlockId(0), 3));
+    assert_eq!(func.instruction_index(InstId(6)), (BlockId(1), 0));
+    assert_eq!(func.instruction_index(InstId(5)), (BlockId(1), 1));
+
+    // The Store goes before the Load in spite of its greater id
+    assert!(func.comes_before(InstId(3), InstId(2)));
+    assert!(!func.comes_before(InstId(2), InstId(3)));
+    assert!(!func.comes_before(InstId(2), InstId(2)));
+    assert!(func.comes_before(InstId(6), InstId(5)));
+}
+
+#[test]
+#[should_panic(expected = "Instructions 0 and 1 are in different blocks")]
+fn comes_before_in_different_blocks() {
+    function(
+        init(3, 2),
+        &[
+            basic_block(0).succs(&[1]).insts(&[inst(0, Opcode::Jump)]),
+            basic_block(1).insts(&[inst(1, Opcode::Parameter), inst(2, Opcode::ReturnVoid)]),
+        ],
+    );
+    get_func().comes_before(InstId(0), InstId(1));
+}
+
+#[test]
+fn rewrite_add_to_sub() {
+    let mut func = build(
+        "
+    fn main(p: i64) -> i64 {
+        if (p == 0) {
+            return p + 1;
+        }
+        return p + 2;
+    }
+    ",
+    );
+
+    // Instructions are visited in the layout order
+    let mut visited = Vec::new();
+    func.rewrite(|id, data| {
+        visited.push(id);
+        match *data {
+            InstData::Add(op1, op2) => Some(InstData::Sub(op1, op2)),
+            _ => None,
+        }
+    });
+    let layout: Vec<InstId> = func
+        .blocks()
+        .iter()
+        .flat_map(|block| block.insts(func.layout()))
+        .collect();
+    assert_eq!(visited, layout);
+
+    assert_eq!(
+        func.dump(),
+        "Function main:
+
+BB 0: preds: [] succs: [1, 2]
+%0 = Parameter
+%1 = Constant 0
+ 2 Branch %0 == %1
+
+BB 1: preds: [0] succs: []
+%3 = Constant 1
+%4 = Sub %0, %3
+ 5 Return %4
+
+BB 2: preds: [0] succs: []
+%6 = Constant 2
+%7 = Sub %0, %6
+ 8 Return %7
+
+"
+    );
+}
@@ -0,0 +1,82 @@
+use crate::optimizer::common::build;
+use ctl::optimizer::if_conversion::if_conversion;
+
+#[test]
+fn convert_diamond_to_select() {
+    let mut func = build(
+        "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = 0;
+        if (p == 0) {
+            a = 1;
+        } else {
+            a = 2;
+        }
+        return a;
+    }
+    ",
+    );
+
+    if_conversion(&mut func);
+    func.verify().unwrap();
+
+    let expected = "Function main:
+
+BB 0: preds: [] succs: [3]
+%0 = Parameter
+%1 = Alloc
+%2 = Constant 0
+ 3 Store %2 at %1
+%5 = Constant 1
+%8 = Constant 2
+%13 = Cmp %0 == %2
+%14 = Select %13, %5, %8
+ 15 Store %14 at %1
+ 4 Jump
+
+BB 1: <empty>
+
+BB 2: <empty>
+
+BB 3: preds: [0] succs: []
+%10 = Load %1
+ 11 Return %10
+
+";
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn keep_diamond_with_side_effects() {
+    let source = "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = 0;
+        if (p == 0) {
+            a = 1;
+        } else {
+            print(p);
+            a = 2;
+        }
+        return a;
+    }
+    ";
+    let mut func = build(source);
+    if_conversion(&mut func);
+    assert_eq!(func, build(source));
+
+    // Different variables are stored on the branches
+    let source = "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = 0, b: i64 = 0;
+        if (p == 0) {
+            a = 1;
+        } else {
+            b = 2;
+        }
+        return a + b;
+    }
+    ";
+    let mut func = build(source);
+    if_conversion(&mut func);
+    assert_eq!(func, build(source));
+}
@@ -0,0 +1,124 @@
+use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
+
+#[test]
+fn pure_instructions() {
+    let op = InstId(0);
+    let pure = [
+        InstData::Constant(1),
+        InstData::Parameter,
+        InstData::Alloc,
+        InstData::Add(op, op),
+        InstData::Sub(op, op),
+        InstData::Mul(op, op),
+        InstData::Shl(op, op),
+        InstData::Shr(op, op),
+        InstData::Neg(op),
+        InstData::Trunc(op),
+        InstData::SignExtend(op),
+        InstData::Cmp(op, op, Cc::Lt),
+        InstData::Select(op, op, op),
+        InstData::Undef,
+        InstData::FuncRef("foo".to_string(), None),
+    ];
+    for data in pure.iter() {
+        assert!(data.is_pure(), "{} should be pure", data);
+    }
+
+    let impure = [
+        // Depends on the previous Stores
+        InstData::Load(op),
+        InstData::Store(op, op),
+        // May trap on division by zero
+        InstData::Div(op, op),
+        InstData::Mod(op, op),
+        InstData::Call("foo".to_string(), None, vec![op]),
+        InstData::CallIndirect(op, vec![op]),
+        InstData::PrintInt(op),
+        InstData::Return(op),
+        InstData::ReturnVoid,
+        InstData::Trap,
+        InstData::IfFalse(op, op, Cc::Eq, op),
+        InstData::Goto(op),
+        InstData::Branch(op, op, Cc::Eq),
+        InstData::Jump,
+    ];
+    for data in impure.iter() {
+        assert!(!data.is_pure(), "{} should not be pure", data);
+    }
+    assert!(!InstData::Invalid.is_pure());
+}
+
+#[test]
+fn opcode_names() {
+    let op = InstId(0);
+    let names = [
+        (InstData::Constant(1), "Constant"),
+        (InstData::Parameter, "Parameter"),
+        (InstData::Alloc, "Alloc"),
+        (InstData::Store(op, op), "Store"),
+        (InstData::Load(op), "Load"),
+        (InstData::Add(op, op), "Add"),
+        (InstData::Sub(op, op), "Sub"),
+        (InstData::Mul(op, op), "Mul"),
+        (InstData::Div(op, op), "Div"),
+        (InstData::Mod(op, op), "Mod"),
+        (InstData::Shl(op, op), "Shl"),
+        (InstData::Shr(op, op), "Shr"),
+        (InstData::Neg(op), "Neg"),
+        (InstData::Trunc(op), "Trunc"),
+        (InstData::SignExtend(op), "SignExtend"),
+        (InstData::Cmp(op, op, Cc::Lt), "Cmp"),
+        (InstData::Select(op, op, op), "Select"),
+        (InstData::Undef, "Undef"),
+        (InstData::Return(op), "Return"),
+        (InstData::ReturnVoid, "ReturnVoid"),
+        (InstData::Trap, "Trap"),
+        (InstData::Call("foo".to_string(), None, vec![op]), "Call"),
+        (InstData::FuncRef("foo".to_string(), None), "FuncRef"),
+        (InstData::CallIndirect(op, vec![op]), "CallIndirect"),
+        (InstData::PrintInt(op), "PrintInt"),
+        (InstData::IfFalse(op, op, Cc::Eq, op), "IfFalse"),
+        (InstData::Goto(op), "Goto"),
+        (InstData::Branch(op, op, Cc::Eq), "Branch"),
+        (InstData::Jump, "Jump"),
+    ];
+
+    for (data, name) in names.iter() {
+        assert_eq!(data.opcode_name(), *name);
+        // The dump starts with the same name
+        assert!(data.to_string().starts_with(name), "{}", data);
+    }
+    assert_eq!(InstData::Invalid.opcode_name(), "Invalid");
+}
+
+#[test]
+fn negate_condition_codes() {
+    let pairs = [(Cc::Eq, Cc::Ne), (Cc::Lt, Cc::Ge), (Cc::Gt, Cc::Le)];
+    for (cc, negated) in pairs.iter() {
+        assert_eq!(cc.negate(), *negated);
+        assert_eq!(negated.negate(), *cc);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Instruction 3 has an unresolved target")]
+fn dump_unresolved_target() {
+    let goto = InstData::Goto(InstId::UNRESOLVED);
+    assert_eq!(goto.to_string(), "Goto ?");
+    goto.dump(InstId(3));
+}
+
+#[test]
+fn eval_condition_codes() {
+    assert!(Cc::Eq.eval(1, 1));
+    assert!(!Cc::Ne.eval(1, 1));
+    assert!(Cc::Lt.eval(-1, 0));
+    assert!(Cc::Gt.eval(2, 1));
+    assert!(Cc::Le.eval(1, 1));
+    assert!(!Cc::Ge.eval(0, 1));
+
+    // Negated condition gives the opposite result
+    for cc in [Cc::Eq, Cc::Ne, Cc::Lt, Cc::Gt, Cc::Le, Cc::Ge] {
+        assert_ne!(cc.eval(3, 5), cc.negate().eval(3, 5));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::optimizer::ir_constructor::{
     basic_block, compare_functions, function, get_func, init, inst, Opcode,
 };
+use ctl::optimizer::ir::basic_block::BlockId;
 use ctl::optimizer::ir::function::Function;
 use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
 use ctl::optimizer::ir_builder::build_intermediate_representation;
@@ -14,7 +15,7 @@ fn build_empty_function() -> Result<(), String> {
     // Linear IR
     func.create_inst(InstData::ReturnVoid);
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -23,7 +24,7 @@ fn build_empty_function() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 #[test]
@@ -35,7 +36,7 @@ fn build_function_2_parameters() -> Result<(), String> {
     func.create_inst(InstData::Parameter);
     func.create_inst(InstData::ReturnVoid);
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -48,7 +49,7 @@ fn build_function_2_parameters() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 #[test]
@@ -59,7 +60,7 @@ fn build_function_returning_param() -> Result<(), String> {
     func.create_inst(InstData::Parameter);
     func.create_inst(InstData::Return(InstId(0)));
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -71,7 +72,34 @@ fn build_function_returning_param() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
+}
+
+#[test]
+fn build_print_to_intrinsic() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of `print(x)` where x is the parameter
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Call("print".to_string(), None, vec![InstId(0)]));
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func)?;
+    assert_eq!(func[InstId(1)].dump(InstId(1)), " 1 PrintInt %0");
+    assert_eq!(func[InstId(1)].operands(), vec![InstId(0)]);
+
+    // Constructing the graph manually
+    function(
+        init(3, 1),
+        &[basic_block(0).insts(&[
+            inst(0, Opcode::Parameter),
+            inst(1, Opcode::PrintInt).inputs(&[0]),
+            inst(2, Opcode::ReturnVoid),
+        ])],
+    );
+
+    // Comparing of what is built with what is constructed manually
+    compare_functions(&func, &get_func())
 }
 
 #[test]
@@ -87,7 +115,7 @@ fn build_function_returning_param_plus_local() -> Result<(), String> {
     func.create_inst(InstData::Add(InstId(0), InstId(4)));
     func.create_inst(InstData::Return(InstId(5)));
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -104,7 +132,7 @@ fn build_function_returning_param_plus_local() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 /// fn main(p: i64) -> i64 {
@@ -125,7 +153,7 @@ fn build_arithmetic_expression() -> Result<(), String> {
     func.create_inst(InstData::Mod(InstId(5), InstId(6)));
     func.create_inst(InstData::Return(InstId(7)));
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -144,7 +172,7 @@ fn build_arithmetic_expression() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 /// fn main(p: i64) -> i64 {
@@ -167,7 +195,7 @@ fn build_conditional_branch_with_assign() -> Result<(), String> {
     func.create_inst(InstData::Store(InstId(5), InstId(1)));
     func.create_inst(InstData::ReturnVoid);
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -190,7 +218,7 @@ fn build_conditional_branch_with_assign() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 /// fn main(p: i64) -> i64 {
@@ -214,53 +242,52 @@ fn build_conditional_branch_with_returns() -> Result<(), String> {
     func.create_inst(InstData::Return(InstId(5)));
     func.create_inst(InstData::ReturnVoid);
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
+    // Goto after the return is in an unreachable block
     function(
-        init(9, 4),
+        init(8, 5),
         &[
-            basic_block(0).succs(&[1, 2]).insts(&[
+            basic_block(0).succs(&[1, 3]).insts(&[
                 inst(0, Opcode::Parameter),
                 inst(1, Opcode::Constant).value(0),
                 inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Eq),
             ]),
-            basic_block(1)
-                .succs(&[3])
-                .insts(&[inst(3, Opcode::Return).inputs(&[1]), inst(4, Opcode::Jump)]),
-            basic_block(2).succs(&[3]).insts(&[
+            basic_block(1).insts(&[inst(3, Opcode::Return).inputs(&[1])]),
+            basic_block(2).succs(&[4]).insts(&[inst(4, Opcode::Jump)]),
+            basic_block(3).insts(&[
                 inst(5, Opcode::Constant).value(1),
                 inst(6, Opcode::Return).inputs(&[5]),
-                inst(8, Opcode::Jump),
             ]),
-            basic_block(3).insts(&[inst(7, Opcode::ReturnVoid)]),
+            basic_block(4).insts(&[inst(7, Opcode::ReturnVoid)]),
         ],
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
 }
 
 /// Input code:
 /// fn main() {
 ///     let mut a: i64 = 0;
 ///     let mut b: i64 = 128;
-
+///
 ///     while (a < 8) {
 ///         a = a + 1;
-
+///
 ///         if (a == 3) {
 ///             continue;
 ///         }
-
+///
 ///         while (b > 0) {
 ///             b = b - 1;
-
+///
 ///             if (b == 4) {
 ///                 continue;
 ///             }
 ///         }
-
+///
 ///     }
 /// }
 #[test]
@@ -332,7 +359,7 @@ fn build_conditional_nested_loops_with_continues() -> Result<(), String> {
 
     func.create_inst(InstData::ReturnVoid);
 
-    build_intermediate_representation(&mut func);
+    build_intermediate_representation(&mut func)?;
 
     // Constructing the graph manually
     function(
@@ -384,5 +411,265 @@ fn build_conditional_nested_loops_with_continues() -> Result<(), String> {
     );
 
     // Comparing of what is built with what is constructed manually
-    compare_functions(&func, get_func())
+    compare_functions(&func, &get_func())
+}
+
+/// fn main(p: i64) {
+///     if (p == 0) {}
+/// }
+#[test]
+fn compare_functions_with_different_preds() {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Constant(0));
+    func.create_inst(InstData::IfFalse(InstId(0), InstId(1), Cc::Eq, InstId(3)));
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func).unwrap();
+
+    // Corrupt the predecessors of the last block, its successors stay the same
+    func.blocks_mut()[1].add_pred(BlockId(1));
+
+    // Constructing the graph manually
+    function(
+        init(4, 2),
+        &[
+            basic_block(0).succs(&[1, 1]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(0),
+                inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(1).insts(&[inst(3, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    assert_eq!(
+        compare_functions(&func, &get_func()),
+        Err("Fields preds differ".to_string())
+    );
+}
+
+#[test]
+fn compare_functions_with_different_instructions() {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Constant(0));
+    func.create_inst(InstData::IfFalse(InstId(0), InstId(1), Cc::Eq, InstId(5)));
+    func.create_inst(InstData::Neg(InstId(0)));
+    func.create_inst(InstData::Return(InstId(3)));
+    func.create_inst(InstData::Return(InstId(1)));
+
+    build_intermediate_representation(&mut func).unwrap();
+
+    // Constructing the graph manually with a wrong input of the Neg instruction
+    function(
+        init(6, 3),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(0),
+                inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(1).insts(&[
+                inst(3, Opcode::Neg).inputs(&[1]),
+                inst(4, Opcode::Return).inputs(&[3]),
+            ]),
+            basic_block(2).insts(&[inst(5, Opcode::Return).inputs(&[1])]),
+        ],
+    );
+
+    assert_eq!(
+        compare_functions(&func, &get_func()),
+        Err("block 1, position 0: Neg %0 vs Neg %1".to_string())
+    );
+}
+
+#[test]
+fn build_backward_conditional_branch() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of a loop which condition is at its bottom: IfFalse targets an earlier
+    // instruction
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Constant(0));
+    func.create_inst(InstData::Neg(InstId(0)));
+    func.create_inst(InstData::IfFalse(InstId(2), InstId(1), Cc::Eq, InstId(2)));
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func)?;
+
+    // Constructing the graph manually. The true successor (fall through) goes first, the false
+    // successor (the loop itself) goes second.
+    function(
+        init(6, 3),
+        &[
+            basic_block(0).succs(&[1]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(0),
+                inst(5, Opcode::Jump),
+            ]),
+            basic_block(1).succs(&[2, 1]).insts(&[
+                inst(2, Opcode::Neg).inputs(&[0]),
+                inst(3, Opcode::Branch).inputs(&[2, 1]).cc(Cc::Eq),
+            ]),
+            basic_block(2).insts(&[inst(4, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    // Comparing of what is built with what is constructed manually
+    compare_functions(&func, &get_func())
+}
+
+#[test]
+fn build_branch_to_last_instruction() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR, IfFalse targets the final ReturnVoid
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::IfFalse(InstId(0), InstId(0), Cc::Eq, InstId(3)));
+    func.create_inst(InstData::Neg(InstId(0)));
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func)?;
+
+    // Constructing the graph manually
+    function(
+        init(5, 3),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Branch).inputs(&[0, 0]).cc(Cc::Eq),
+            ]),
+            basic_block(1)
+                .succs(&[2])
+                .insts(&[inst(2, Opcode::Neg).inputs(&[0]), inst(4, Opcode::Jump)]),
+            basic_block(2).insts(&[inst(3, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    // Comparing of what is built with what is constructed manually
+    compare_functions(&func, &get_func())
+}
+
+#[test]
+fn build_branch_out_of_function() {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR without the final ReturnVoid, IfFalse targets the instruction after the last one
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::IfFalse(InstId(0), InstId(0), Cc::Eq, InstId(3)));
+    func.create_inst(InstData::Return(InstId(0)));
+
+    assert_eq!(
+        build_intermediate_representation(&mut func),
+        Err("Instruction 1 branches to 3 which is out of 3 instructions".to_string())
+    );
+}
+
+#[test]
+fn build_assert_to_trap() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of `assert(x == 1)` where x is the parameter
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Constant(1));
+    func.create_inst(InstData::IfFalse(InstId(0), InstId(1), Cc::Ne, InstId(4)));
+    func.create_inst(InstData::Trap);
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func)?;
+    func.verify()?;
+
+    // Constructing the graph manually
+    function(
+        init(5, 3),
+        &[
+            basic_block(0).succs(&[1, 2]).insts(&[
+                inst(0, Opcode::Parameter),
+                inst(1, Opcode::Constant).value(1),
+                inst(2, Opcode::Branch).inputs(&[0, 1]).cc(Cc::Ne),
+            ]),
+            basic_block(1).insts(&[inst(3, Opcode::Trap)]),
+            basic_block(2).insts(&[inst(4, Opcode::ReturnVoid)]),
+        ],
+    );
+
+    // Comparing of what is built with what is constructed manually
+    compare_functions(&func, &get_func())
+}
+
+#[test]
+fn build_unresolved_target() {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of a loop which Goto is not wired up
+    func.create_inst(InstData::Goto(InstId::UNRESOLVED));
+    func.create_inst(InstData::ReturnVoid);
+
+    assert_eq!(
+        build_intermediate_representation(&mut func),
+        Err("Instruction 0 has an unresolved target".to_string())
+    );
+}
+
+#[test]
+fn build_fall_through_end() {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of a function which last instruction is not a terminator
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Neg(InstId(0)));
+
+    assert_eq!(
+        build_intermediate_representation(&mut func),
+        Err("Instruction 1 falls through the end of the function".to_string())
+    );
+}
+
+#[test]
+fn build_infinite_loop_without_return() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR of `while (true) {}` as the last statement
+    func.create_inst(InstData::Parameter);
+    func.create_inst(InstData::Goto(InstId(1)));
+
+    build_intermediate_representation(&mut func)?;
+    func.verify()?;
+    assert!(func.exit_blocks().is_empty());
+    assert!(!func.has_reachable_exit());
+    Ok(())
+}
+
+#[test]
+fn build_dead_code_after_return() -> Result<(), String> {
+    let mut func = Function::new("".to_string());
+
+    // Linear IR
+    func.create_inst(InstData::ReturnVoid);
+    func.create_inst(InstData::Constant(1));
+    func.create_inst(InstData::PrintInt(InstId(1)));
+    func.create_inst(InstData::ReturnVoid);
+
+    build_intermediate_representation(&mut func)?;
+    func.verify()?;
+
+    // Instructions after the return are in an unreachable block
+    function(
+        init(4, 2),
+        &[
+            basic_block(0).insts(&[inst(0, Opcode::ReturnVoid)]),
+            basic_block(1).insts(&[
+                inst(1, Opcode::Constant).value(1),
+                inst(2, Opcode::PrintInt).inputs(&[1]),
+                inst(3, Opcode::ReturnVoid),
+            ]),
+        ],
+    );
+
+    compare_functions(&func, &get_func())
 }
@@ -1,77 +1,75 @@
+use std::cell::{Cell, RefCell};
+
 use ctl::optimizer::ir::basic_block::{BasicBlock, BlockId, InstNode};
 use ctl::optimizer::ir::function::Function;
 use ctl::optimizer::ir::inst::{Cc, InstData, InstId};
 
-struct FuncRaw(*mut Function);
-unsafe impl Sync for FuncRaw {}
+// Every test thread constructs its own Function, so the tests can run in parallel.
+thread_local! {
+    static FUNC: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static CUR_BLOCK: Cell<BlockId> = const { Cell::new(BlockId(0)) };
+    static CUR_INST: Cell<InstId> = const { Cell::new(InstId(0)) };
+}
 
-impl FuncRaw {
-    fn get(&self) -> *mut Function {
-        self.0
-    }
+/// Calls `f` with the Function which is being constructed.
+fn with_func<R>(f: impl FnOnce(&mut Function) -> R) -> R {
+    FUNC.with(|func| {
+        f(func
+            .borrow_mut()
+            .as_mut()
+            .expect("Function is not initialized, init() should be called first"))
+    })
+}
 
-    fn set(&mut self, value: *mut Function) {
-        self.0 = value;
-    }
+fn cur_block() -> BlockId {
+    CUR_BLOCK.with(Cell::get)
 }
 
-const NULL_POINTER: *mut Function = 0 as *mut Function;
+fn cur_inst() -> InstId {
+    CUR_INST.with(Cell::get)
+}
 
-static mut FUNC: FuncRaw = FuncRaw(NULL_POINTER);
+/// Takes the data of the current instruction out of the Function, the placeholder Invalid is left
+/// instead. It is written back by `set_cur_inst_data`.
+fn take_cur_inst_data() -> InstData {
+    with_func(|func| std::mem::replace(&mut func.insts_mut()[cur_inst().0], InstData::Invalid))
+}
 
-static mut CUR_BLOCK: BlockId = BlockId(0);
-static mut CUR_INST: InstId = InstId(0);
+fn set_cur_inst_data(data: InstData) {
+    with_func(|func| func.insts_mut()[cur_inst().0] = data);
+}
 
-pub fn get_func() -> &'static mut Function {
-    unsafe { &mut *FUNC.get() }
+/// Takes the constructed Function.
+pub fn get_func() -> Function {
+    FUNC.with(|func| {
+        func.borrow_mut()
+            .take()
+            .expect("Function is not initialized, init() should be called first")
+    })
 }
 
 pub struct Constructor {}
 
 /// After filling the Function with the basic blocks this adds the predecessors
 pub fn function(_insts_len: Constructor, _blocks: &[Constructor]) {
-    unsafe {
-        debug_assert_ne!(FUNC.get(), NULL_POINTER);
-
-        for (id, block) in (*FUNC.get()).blocks_mut().iter().enumerate() {
-            for succ in block.succs() {
-                (*FUNC.get()).blocks_mut()[succ.0].add_pred(BlockId(id));
-            }
-        }
-    }
+    with_func(Function::rebuild_predecessors);
 }
 
 pub fn init(insts_len: usize, blocks_len: usize) -> Constructor {
-    // Allocate memory for Function using Box
-    let smart = Box::new(Function::new(Default::default()));
-    let func_raw = Box::into_raw(smart);
-
-    unsafe {
-        if FUNC.get() != NULL_POINTER {
-            // Free memory from old Function
-            {
-                Box::from_raw(FUNC.get());
-            }
-        }
-        FUNC.set(func_raw);
-    }
+    let mut func = Function::new(Default::default());
 
     // Initialize insts, layout and blocks
-    unsafe {
-        *(*FUNC.get()).insts_mut() = vec![InstData::Invalid; insts_len];
-        *(*FUNC.get()).layout_mut() = vec![InstNode::new(); insts_len];
+    *func.insts_mut() = vec![InstData::Invalid; insts_len];
+    *func.layout_mut() = vec![InstNode::new(); insts_len];
+    *func.blocks_mut() = vec![BasicBlock::new(); blocks_len];
 
-        *(*FUNC.get()).blocks_mut() = vec![BasicBlock::new(); blocks_len];
-    }
+    FUNC.with(|f| *f.borrow_mut() = Some(func));
 
     Constructor {}
 }
 
 pub fn basic_block(id: usize) -> Constructor {
-    unsafe {
-        debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        CUR_BLOCK = BlockId(id);
-    }
+    with_func(|_| CUR_BLOCK.with(|block| block.set(BlockId(id))));
 
     Constructor {}
 }
@@ -91,9 +89,17 @@ pub enum Opcode {
     Shl,
     Shr,
     Neg,
+    Trunc,
+    SignExtend,
+    Cmp,
+    Select,
+    Undef,
     Return,
     ReturnVoid,
+    Trap,
     Call,
+    CallIndirect,
+    PrintInt,
     Branch,
     Jump,
 }
@@ -114,33 +120,39 @@ pub fn inst(id: usize, opcode: Opcode) -> Constructor {
         Opcode::Shl => InstData::Shl(Default::default(), Default::default()),
         Opcode::Shr => InstData::Shr(Default::default(), Default::default()),
         Opcode::Neg => InstData::Neg(Default::default()),
+        Opcode::Trunc => InstData::Trunc(Default::default()),
+        Opcode::SignExtend => InstData::SignExtend(Default::default()),
+        Opcode::Cmp => InstData::Cmp(Default::default(), Default::default(), Cc::Invalid),
+        Opcode::Select => {
+            InstData::Select(Default::default(), Default::default(), Default::default())
+        }
+        Opcode::Undef => InstData::Undef,
         Opcode::Return => InstData::Return(Default::default()),
         Opcode::ReturnVoid => InstData::ReturnVoid,
-        Opcode::Call => InstData::Call(Default::default(), Default::default()),
+        Opcode::Trap => InstData::Trap,
+        Opcode::Call => InstData::Call(Default::default(), None, Default::default()),
+        Opcode::CallIndirect => InstData::CallIndirect(Default::default(), Default::default()),
+        Opcode::PrintInt => InstData::PrintInt(Default::default()),
         Opcode::Branch => InstData::Branch(Default::default(), Default::default(), Cc::Invalid),
         Opcode::Jump => InstData::Jump,
     };
 
-    unsafe {
-        debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        CUR_INST = InstId(id);
-        (*FUNC.get()).insts_mut()[CUR_INST.0] = data;
-        (*FUNC.get()).append_inst(CUR_INST, CUR_BLOCK);
-    }
+    CUR_INST.with(|inst| inst.set(InstId(id)));
+    with_func(|func| {
+        func.insts_mut()[id] = data;
+        func.append_inst(InstId(id), cur_block());
+    });
 
     Constructor {}
 }
 
 impl Constructor {
     pub fn succs(&self, elems: &[usize]) -> Self {
-        unsafe {
-            debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        }
-        for el in elems {
-            unsafe {
-                (*FUNC.get()).blocks_mut()[CUR_BLOCK.0].add_succ(BlockId(*el));
+        with_func(|func| {
+            for el in elems {
+                func.blocks_mut()[cur_block().0].add_succ(BlockId(*el));
             }
-        }
+        });
         Constructor {}
     }
 
@@ -149,26 +161,21 @@ impl Constructor {
     }
 
     pub fn value(&self, data: i64) -> Self {
-        unsafe {
-            debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        }
-
-        let inst_data: &mut InstData = unsafe { &mut (*FUNC.get()).insts_mut()[CUR_INST.0] };
+        let mut cur = take_cur_inst_data();
+        let inst_data: &mut InstData = &mut cur;
         match inst_data {
             InstData::Constant(ref mut value) => *value = data,
             _ => panic!("value() called not for Constant instruction"),
         }
+        set_cur_inst_data(cur);
 
         Constructor {}
     }
 
     /// Sets inputs to an instruction.
     pub fn inputs(&self, args: &[usize]) -> Self {
-        unsafe {
-            debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        }
-
-        let inst_data: &mut InstData = unsafe { &mut (*FUNC.get()).insts_mut()[CUR_INST.0] };
+        let mut cur = take_cur_inst_data();
+        let inst_data: &mut InstData = &mut cur;
 
         match inst_data {
             InstData::Store(ref mut value, _) => {
@@ -176,7 +183,7 @@ impl Constructor {
                     args.len(),
                     1,
                     "Instruction with ID {}: Store should have only one input (value to store) but {} inputs were given",
-                    unsafe{ CUR_INST.0 }, args.len()
+                    cur_inst().0, args.len()
                 );
                 *value = InstId(args[0]);
             }
@@ -185,7 +192,7 @@ impl Constructor {
                     args.len(),
                     1,
                     "Instruction with ID {}: Load should have only one input (pointer to the variable) but {} inputs were given",
-                    unsafe{ CUR_INST.0 }, args.len()
+                    cur_inst().0, args.len()
                 );
                 *ptr = InstId(args[0]);
             }
@@ -195,7 +202,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Add should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -206,7 +213,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Sub should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -217,7 +224,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Mul should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -228,7 +235,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Div should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -239,7 +246,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Mod should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -250,7 +257,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Shl should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -261,7 +268,7 @@ impl Constructor {
                     args.len(),
                     2,
                     "Instruction with ID {}: Shr should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -273,7 +280,16 @@ impl Constructor {
                     args.len(),
                     1,
                     "Instruction with ID {}: Neg should have only one input (value) but {} inputs given",
-                    unsafe { CUR_INST.0 }, args.len()
+                    cur_inst().0, args.len()
+                );
+                *op = InstId(args[0]);
+            }
+            InstData::Trunc(ref mut op) | InstData::SignExtend(ref mut op) => {
+                debug_assert_eq!(
+                    args.len(),
+                    1,
+                    "Instruction with ID {}: conversion should have only one input (value) but {} inputs given",
+                    cur_inst().0, args.len()
                 );
                 *op = InstId(args[0]);
             }
@@ -283,12 +299,12 @@ impl Constructor {
                     args.len(),
                     1,
                     "Instruction with ID {}: Return should have only one input (value) but {} inputs given",
-                    unsafe { CUR_INST.0 }, args.len()
+                    cur_inst().0, args.len()
                 );
                 *value = InstId(args[0]);
             }
 
-            InstData::Call(_, ref mut params) => {
+            InstData::Call(_, _, ref mut params) => {
                 let mut ids = Vec::new();
                 for arg in args {
                     ids.push(InstId(*arg));
@@ -296,13 +312,57 @@ impl Constructor {
 
                 *params = ids;
             }
+            InstData::CallIndirect(ref mut callee, ref mut params) => {
+                debug_assert!(
+                    !args.is_empty(),
+                    "Instruction with ID {}: CallIndirect should have the callee input",
+                    cur_inst().0
+                );
+                *callee = InstId(args[0]);
+                *params = args[1..].iter().map(|arg| InstId(*arg)).collect();
+            }
+
+            InstData::PrintInt(ref mut value) => {
+                debug_assert_eq!(
+                    args.len(),
+                    1,
+                    "Instruction with ID {}: PrintInt should have only one input (value) but {} inputs given",
+                    cur_inst().0, args.len()
+                );
+                *value = InstId(args[0]);
+            }
+
+            InstData::Cmp(ref mut op1, ref mut op2, _) => {
+                debug_assert_eq!(
+                    args.len(),
+                    2,
+                    "Instruction with ID {}: Cmp should have only 2 inputs but {} were given",
+                    cur_inst().0,
+                    args.len()
+                );
+                *op1 = InstId(args[0]);
+                *op2 = InstId(args[1]);
+            }
+
+            InstData::Select(ref mut cond, ref mut op1, ref mut op2) => {
+                debug_assert_eq!(
+                    args.len(),
+                    3,
+                    "Instruction with ID {}: Select should have only 3 inputs but {} were given",
+                    cur_inst().0,
+                    args.len()
+                );
+                *cond = InstId(args[0]);
+                *op1 = InstId(args[1]);
+                *op2 = InstId(args[2]);
+            }
 
             InstData::Branch(ref mut op1, ref mut op2, _) => {
                 debug_assert_eq!(
                     args.len(),
                     2,
                     "Instruction with ID {}: Branch should have only 2 inputs but {} were given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 );
                 *op1 = InstId(args[0]);
@@ -313,10 +373,13 @@ impl Constructor {
             | InstData::Constant(_)
             | InstData::Jump
             | InstData::Parameter
-            | InstData::ReturnVoid => {
+            | InstData::ReturnVoid
+            | InstData::Trap
+            | InstData::Undef
+            | InstData::FuncRef(_, _) => {
                 panic!(
                     "Instruction with ID {}: should not have an input but {} inputs given",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                     args.len()
                 )
             }
@@ -324,78 +387,92 @@ impl Constructor {
             InstData::IfFalse(_, _, _, _) | InstData::Goto(_) => {
                 panic!(
                     "Instruction with ID {}: Such an instruction should not be in current stage",
-                    unsafe { CUR_INST.0 },
+                    cur_inst().0,
                 )
             }
 
             InstData::Invalid => panic!("Invalid should not be created in the ir constructor"),
         };
+        set_cur_inst_data(cur);
 
         Constructor {}
     }
 
     /// Sets destination operand to Store instruction.
     pub fn dest(&self, d: usize) -> Self {
-        unsafe {
-            debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        }
-
-        let inst_data: &mut InstData = unsafe { &mut (*FUNC.get()).insts_mut()[CUR_INST.0] };
+        let mut cur = take_cur_inst_data();
+        let inst_data: &mut InstData = &mut cur;
         match inst_data {
             InstData::Store(_, ref mut dest) => {
+                // Only a variable can be a destination, the Alloc may be created later
+                with_func(|func| {
+                    assert!(
+                        matches!(func.insts()[d], InstData::Alloc | InstData::Invalid),
+                        "Instruction with ID {}: destination of Store should be Alloc but it is {}",
+                        cur_inst().0,
+                        func.insts()[d]
+                    )
+                });
                 *dest = InstId(d);
             }
 
             _ => panic!("Only the Store instruction can have a destination"),
         };
+        set_cur_inst_data(cur);
 
         Constructor {}
     }
 
-    /// Sets the condition code to Branch instruction.
+    /// Sets the condition code to Branch or Cmp instruction.
     pub fn cc(&self, cond: Cc) -> Self {
-        unsafe {
-            debug_assert_ne!(FUNC.get(), NULL_POINTER);
-        }
-
-        let inst_data: &mut InstData = unsafe { &mut (*FUNC.get()).insts_mut()[CUR_INST.0] };
+        let mut cur = take_cur_inst_data();
+        let inst_data: &mut InstData = &mut cur;
         match inst_data {
-            InstData::Branch(_, _, ref mut c) => {
+            InstData::Branch(_, _, ref mut c) | InstData::Cmp(_, _, ref mut c) => {
                 *c = cond;
             }
 
             InstData::IfFalse(_, _, _, _) => panic!("IfFalse should not be at this stage"),
-            _ => panic!("Only the Branch instruction can have a condition code"),
+            _ => panic!("Only the Branch and Cmp instructions can have a condition code"),
         };
+        set_cur_inst_data(cur);
 
         Constructor {}
     }
 }
 
+fn inst_to_string(data: &InstData) -> String {
+    match data {
+        InstData::Invalid => "Invalid".to_string(),
+        _ => data.to_string(),
+    }
+}
+
 pub fn compare_functions(f1: &Function, f2: &Function) -> Result<(), String> {
     if f1.blocks().len() != f2.blocks().len() {
         return Err("Different length of the blocks".to_string());
     }
 
     for (id, block) in f1.blocks().iter().enumerate() {
+        if block.preds() != f2.blocks()[id].preds() {
+            return Err("Fields preds differ".to_string());
+        }
+
         if block.succs() != f2.blocks()[id].succs() {
             return Err("Fields succs differ".to_string());
         }
 
-        let b = BlockId(id);
-        unsafe {
-            let mut to_inst1 = f1.blocks()[b.0].first() as *const Option<InstId>;
-            while let Some(inst_id) = *to_inst1 {
-                let in1 = &f1.insts()[inst_id.0];
-                let in2 = &f2.insts()[inst_id.0];
-                if in1 != in2 {
-                    return Err(format!(
-                        "Instructions differ: ({}) and ({})",
-                        in1.dump(inst_id),
-                        in2.dump(inst_id)
-                    ));
-                }
-                to_inst1 = f1.layout()[inst_id.0].next() as *const Option<InstId>;
+        for (position, inst_id) in block.insts(f1.layout()).enumerate() {
+            let in1 = &f1.insts()[inst_id.0];
+            let in2 = &f2.insts()[inst_id.0];
+            if in1 != in2 {
+                return Err(format!(
+                    "block {}, position {}: {} vs {}",
+                    id,
+                    position,
+                    inst_to_string(in1),
+                    inst_to_string(in2)
+                ));
             }
         }
     }
@@ -404,5 +481,5 @@ pub fn compare_functions(f1: &Function, f2: &Function) -> Result<(), String> {
 }
 
 pub fn dump() -> String {
-    unsafe { (*FUNC.get()).dump() }
+    with_func(|func| func.dump())
 }
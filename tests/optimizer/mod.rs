@@ -1,2 +1,15 @@
+pub mod basic_block_test;
+pub mod block_map_test;
+pub mod canonicalize_test;
+pub mod common;
+pub mod fold_branches_test;
+pub mod function_test;
+pub mod if_conversion_test;
+pub mod inst_test;
 pub mod ir_builder_test;
 pub mod ir_constructor;
+pub mod module_test;
+pub mod pipeline_test;
+pub mod remove_unreachable_test;
+pub mod specialize_test;
+pub mod unused_locals_test;
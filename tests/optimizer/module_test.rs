@@ -0,0 +1,89 @@
+use ctl::frontend::inst_builder::generate_module;
+use ctl::frontend::parser::parse;
+use ctl::optimizer::ir::inst::{InstData, InstId};
+use ctl::optimizer::ir::module::FuncId;
+
+#[test]
+fn resolve_calls_in_module() {
+    let source = "
+    fn foo(p: i64) {}
+
+    fn main() {
+        foo(1);
+        print(2);
+    }
+    "
+    .to_string();
+
+    let funcs = parse(source).unwrap();
+    let module = generate_module(&funcs).unwrap();
+
+    assert_eq!(module.functions().len(), 2);
+    assert_eq!(module.function_by_name("foo"), Some(FuncId(0)));
+    assert_eq!(module.function_by_name("main"), Some(FuncId(1)));
+    assert_eq!(module.function_by_name("print"), None);
+
+    let calls: Vec<&InstData> = module[FuncId(1)]
+        .insts()
+        .iter()
+        .filter(|data| matches!(data, InstData::Call(_, _, _)))
+        .collect();
+    assert_eq!(calls.len(), 2);
+
+    // Call of the user function is resolved to its index
+    match calls[0] {
+        InstData::Call(name, callee, _) => {
+            assert_eq!(name, "foo");
+            assert_eq!(*callee, Some(FuncId(0)));
+        }
+        _ => std::unreachable!(),
+    }
+
+    // Built-in function is not a part of the module
+    match calls[1] {
+        InstData::Call(name, callee, _) => {
+            assert_eq!(name, "print");
+            assert_eq!(*callee, None);
+        }
+        _ => std::unreachable!(),
+    }
+}
+
+#[test]
+fn resolve_function_references() {
+    let source = "
+    fn zero() -> i64 {
+        return 0;
+    }
+
+    fn main() -> i64 {
+        let mut f: i64 = zero, g: i64 = main;
+        f();
+        return f();
+    }
+    ";
+
+    let funcs = parse(source).unwrap();
+    let module = generate_module(&funcs).unwrap();
+    let main = &module[FuncId(1)];
+
+    // Both functions are referenced by their indices, the calls go through the loaded values
+    let expected = [
+        "%0 = Alloc",
+        "%1 = FuncRef zero",
+        " 2 Store %1 at %0",
+        "%3 = Alloc",
+        "%4 = FuncRef main",
+        " 5 Store %4 at %3",
+        "%6 = Load %0",
+        "%7 = CallIndirect %6, args: ",
+        "%8 = Load %0",
+        "%9 = CallIndirect %8, args: ",
+        " 10 Return %9",
+    ];
+    for (i, line) in expected.iter().enumerate() {
+        assert_eq!(main[InstId(i)].dump(InstId(i)), *line);
+    }
+    assert!(main[InstId(1)] == InstData::FuncRef("zero".to_string(), Some(FuncId(0))));
+    assert!(main[InstId(4)] == InstData::FuncRef("main".to_string(), Some(FuncId(1))));
+}
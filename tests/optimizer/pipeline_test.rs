@@ -0,0 +1,55 @@
+use crate::optimizer::common::build;
+use ctl::optimizer::pipeline::{run_passes, OptLevel};
+
+const SOURCE: &str = "
+    fn main(p: i64) -> i64 {
+        let mut a: i64 = 0;
+        if (1 as i64 > 2) {
+            a = 1;
+        }
+        if (p == 0) {
+            a = 3;
+        } else {
+            a = 4;
+        }
+        return a;
+    }
+    ";
+
+#[test]
+fn parse_opt_levels() {
+    assert_eq!(OptLevel::from_option("-O0"), Some(OptLevel::O0));
+    assert_eq!(OptLevel::from_option("-O1"), Some(OptLevel::O1));
+    assert_eq!(OptLevel::from_option("-O2"), Some(OptLevel::O2));
+    assert_eq!(OptLevel::from_option("-O3"), None);
+    assert_eq!(OptLevel::default(), OptLevel::O0);
+}
+
+#[test]
+fn run_passes_of_each_level() {
+    let func = build(SOURCE);
+
+    // Nothing changes at O0
+    let mut o0 = func.clone();
+    run_passes(&mut o0, OptLevel::O0).unwrap();
+    assert_eq!(o0, func);
+    assert_eq!(o0.blocks().len(), 6);
+
+    // The constant branch is folded and the never taken assignment is removed
+    let mut o1 = func.clone();
+    let passes: Vec<&str> = run_passes(&mut o1, OptLevel::O1)
+        .unwrap()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect();
+    assert_eq!(passes, ["fold_branches", "remove_unreachable_blocks"]);
+    assert_eq!(o1.blocks().len(), 5);
+    assert!(o1.dump().contains("Branch %0 == %2"));
+
+    // The diamond becomes a Select
+    let mut o2 = func;
+    run_passes(&mut o2, OptLevel::O2).unwrap();
+    assert_eq!(o2.blocks().len(), 3);
+    assert!(o2.dump().contains("Select"));
+    assert!(!o2.dump().contains("Branch"));
+}
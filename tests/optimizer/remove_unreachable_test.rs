@@ -0,0 +1,66 @@
+use crate::optimizer::common::build;
+use ctl::optimizer::fold_branches::fold_branches;
+use ctl::optimizer::ir::basic_block::BlockId;
+use ctl::optimizer::remove_unreachable::remove_unreachable_blocks;
+
+#[test]
+fn remove_dead_code_after_return() {
+    let mut func = build(
+        "
+    fn main(p: i64) {
+        if (p == 0) {
+            return;
+            print(1);
+        }
+        print(1);
+    }
+    ",
+    );
+    assert_eq!(func.blocks().len(), 4);
+
+    remove_unreachable_blocks(&mut func);
+    func.verify().unwrap();
+
+    // Constant 1 of the removed block is still printed after the if, so it is moved to the entry
+    let expected = "Function main:
+
+BB 0: preds: [] succs: [1, 2]
+%0 = Parameter
+%1 = Constant 0
+%4 = Constant 1
+ 2 Branch %0 == %1
+
+BB 1: preds: [0] succs: []
+ 3 ReturnVoid
+
+BB 2: preds: [0] succs: []
+ 6 PrintInt %4
+ 7 ReturnVoid
+
+";
+    assert_eq!(func.dump(), expected);
+}
+
+#[test]
+fn remove_blocks_of_folded_branch() {
+    let mut func = build(
+        "
+    fn main(p: i64) {
+        if (1 as i64 > 2) {
+            print(p);
+        } else {
+            print(2);
+        }
+    }
+    ",
+    );
+    fold_branches(&mut func);
+    remove_unreachable_blocks(&mut func);
+    func.verify().unwrap();
+
+    // The true successor is gone, the other blocks are renumbered
+    assert_eq!(func.blocks().len(), 3);
+    assert_eq!(func.succs_of(BlockId(0)), &[BlockId(1)]);
+    assert_eq!(func.succs_of(BlockId(1)), &[BlockId(2)]);
+    assert!(func.reachable_blocks().iter_enumerated().all(|(_, r)| *r));
+}
@@ -0,0 +1,108 @@
+use ctl::frontend::inst_builder::generate_module;
+use ctl::frontend::parser::parse;
+use ctl::optimizer::ir::module::{FuncId, Module};
+use ctl::optimizer::ir_builder::build_intermediate_representation;
+use ctl::optimizer::specialize::specialize_single_caller;
+
+fn build_module(source: &str) -> Module {
+    let funcs = parse(source).unwrap();
+    let mut module = generate_module(&funcs).unwrap();
+    for func in module.functions_mut() {
+        build_intermediate_representation(func).unwrap();
+    }
+
+    module
+}
+
+#[test]
+fn specialize_function_with_single_caller() {
+    let mut module = build_module(
+        "
+    fn g(x: i64) -> i64 {
+        return x + 1;
+    }
+
+    fn main() {
+        let mut a: i64 = g(41);
+    }
+    ",
+    );
+
+    specialize_single_caller(&mut module);
+
+    let g = &module[FuncId(0)];
+    assert_eq!(g.params(), 0);
+    assert_eq!(
+        g.dump(),
+        "Function g:
+
+BB 0: preds: [] succs: []
+%0 = Constant 41
+%1 = Constant 1
+%2 = Add %0, %1
+ 3 Return %2
+
+"
+    );
+    assert!(module[FuncId(1)].dump().contains("Call g, args: \n"));
+    for func in module.functions() {
+        assert_eq!(func.verify(), Ok(()));
+    }
+}
+
+#[test]
+fn keep_function_with_several_callers() {
+    let source = "
+    fn g(x: i64) -> i64 {
+        return x + 1;
+    }
+
+    fn main(p: i64) {
+        let mut a: i64 = g(41);
+        a = g(p);
+    }
+    ";
+    let mut module = build_module(source);
+    let before = module[FuncId(0)].clone();
+
+    specialize_single_caller(&mut module);
+    assert!(module[FuncId(0)] == before);
+
+    // The only call with an argument which is not a constant
+    let mut module = build_module(
+        "
+    fn g(x: i64) -> i64 {
+        return x + 1;
+    }
+
+    fn main(p: i64) {
+        let mut a: i64 = g(p);
+    }
+    ",
+    );
+    let before = module[FuncId(0)].clone();
+
+    specialize_single_caller(&mut module);
+    assert!(module[FuncId(0)] == before);
+}
+
+#[test]
+fn keep_referenced_function() {
+    // The function may be called indirectly with other arguments
+    let mut module = build_module(
+        "
+    fn g(x: i64) -> i64 {
+        return x + 1;
+    }
+
+    fn main() {
+        let mut a: i64 = g(41), f: i64 = g;
+        a = f(a);
+    }
+    ",
+    );
+    let before = module[FuncId(0)].clone();
+
+    specialize_single_caller(&mut module);
+    assert!(module[FuncId(0)] == before);
+}
@@ -0,0 +1,26 @@
+use ctl::frontend::inst_builder::generate_instructions;
+use ctl::frontend::parser::parse;
+use ctl::optimizer::ir::inst::InstId;
+use ctl::optimizer::unused_locals::find_unused_locals;
+
+#[test]
+fn find_never_read_variables() {
+    let source = "
+    fn main(p: i64) -> i64 {
+        let mut x: i64 = 0, y: i64 = 1, z: i64;
+        x = p;
+        return y;
+    }
+    ";
+    let funcs = parse(source).unwrap();
+    let func = generate_instructions(&funcs[0]).unwrap();
+
+    // x is only written, z is not used at all, y is read
+    assert_eq!(find_unused_locals(&func), vec![InstId(1), InstId(7)]);
+
+    // Allocs are mapped back to the source variables for the warning
+    assert_eq!(func.var_name(InstId(1)), Some("x"));
+    assert_eq!(func.var_name(InstId(7)), Some("z"));
+    assert_eq!(func.source_line(InstId(7)), Some(3));
+    assert_eq!(func.var_name(InstId(0)), None);
+}